@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS time_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id INTEGER NOT NULL,
+    activity_id INTEGER,
+    app_id TEXT NOT NULL,
+    window_title TEXT NOT NULL,
+    focus_start TEXT NOT NULL,
+    focus_end TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+    FOREIGN KEY (activity_id) REFERENCES window_activities(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_time_logs_focus_start ON time_logs(focus_start);
+CREATE INDEX IF NOT EXISTS idx_time_logs_session_id ON time_logs(session_id);
+CREATE INDEX IF NOT EXISTS idx_time_logs_app_id ON time_logs(app_id);
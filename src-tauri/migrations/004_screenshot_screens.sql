@@ -0,0 +1,9 @@
+ALTER TABLE screenshots ADD COLUMN screen_id INTEGER;
+ALTER TABLE screenshots ADD COLUMN screen_x INTEGER;
+ALTER TABLE screenshots ADD COLUMN screen_y INTEGER;
+ALTER TABLE screenshots ADD COLUMN screen_width INTEGER;
+ALTER TABLE screenshots ADD COLUMN screen_height INTEGER;
+ALTER TABLE screenshots ADD COLUMN scale_factor REAL;
+ALTER TABLE screenshots ADD COLUMN is_composite INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_screenshots_timestamp_screen ON screenshots(timestamp, screen_id);
@@ -0,0 +1,5 @@
+ALTER TABLE screenshots ADD COLUMN checksum TEXT;
+ALTER TABLE screenshots ADD COLUMN integrity TEXT;
+ALTER TABLE screenshots ADD COLUMN verified_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_screenshots_verified_at ON screenshots(verified_at);
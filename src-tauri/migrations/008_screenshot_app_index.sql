@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS idx_screenshots_app_id ON screenshots(app_id);
@@ -0,0 +1,4 @@
+ALTER TABLE screenshots ADD COLUMN phash INTEGER;
+ALTER TABLE screenshots ADD COLUMN unchanged_of INTEGER REFERENCES screenshots(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_screenshots_unchanged_of ON screenshots(unchanged_of);
@@ -0,0 +1,2 @@
+ALTER TABLE screenshots ADD COLUMN privacy_action TEXT;
+ALTER TABLE screenshots ADD COLUMN privacy_rule TEXT;
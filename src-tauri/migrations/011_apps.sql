@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS apps (
+    id INTEGER PRIMARY KEY,
+    stable_id TEXT NOT NULL UNIQUE,
+    display_name TEXT NOT NULL,
+    exe_path TEXT,
+    desktop_entry TEXT,
+    wm_class TEXT,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL
+);
+
+ALTER TABLE window_activities ADD COLUMN app_ref INTEGER REFERENCES apps(id);
+ALTER TABLE time_logs ADD COLUMN app_ref INTEGER REFERENCES apps(id);
+
+-- Rows recorded before the registry only know the reported app name
+INSERT OR IGNORE INTO apps (stable_id, display_name, first_seen, last_seen)
+SELECT 'name:' || app_id, app_id, MIN(timestamp), MAX(timestamp)
+FROM window_activities
+GROUP BY app_id;
+
+UPDATE window_activities
+SET app_ref = (SELECT id FROM apps WHERE apps.stable_id = 'name:' || window_activities.app_id);
+
+UPDATE time_logs
+SET app_ref = (SELECT id FROM apps WHERE apps.stable_id = 'name:' || time_logs.app_id);
+
+CREATE INDEX IF NOT EXISTS idx_window_activities_app_ref ON window_activities(app_ref);
+CREATE INDEX IF NOT EXISTS idx_time_logs_app_ref ON time_logs(app_ref);
@@ -0,0 +1,75 @@
+CREATE TABLE IF NOT EXISTS app_transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id INTEGER NOT NULL,
+    from_app_id TEXT NOT NULL,
+    from_app_ref INTEGER REFERENCES apps(id),
+    to_app_id TEXT NOT NULL,
+    to_app_ref INTEGER REFERENCES apps(id),
+    transition_type TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    -- Seconds spent in the previous app (for switches within an app, the previous window)
+    dwell INTEGER NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+
+-- Earlier focus rows only tell app switches from window switches. A focus row
+-- follows its predecessor directly when the predecessor's interval ran up to it;
+-- otherwise tracking was paused or idle in between and there was no switch.
+-- A "stint" is a run of directly following focus rows of one app.
+INSERT INTO app_transitions (session_id, from_app_id, from_app_ref, to_app_id, to_app_ref, transition_type, timestamp, dwell)
+SELECT
+    session_id,
+    previous_app_id,
+    previous_app_ref,
+    app_id,
+    app_ref,
+    CASE WHEN new_stint THEN 'app_switch' ELSE 'window_switch' END,
+    timestamp,
+    CASE WHEN new_stint
+         THEN CAST((julianday(timestamp) - julianday(previous_stint_start)) * 86400 AS INTEGER)
+         ELSE previous_duration END
+FROM (
+    SELECT
+        *,
+        LAG(stint_start) OVER focus_order as previous_stint_start
+    FROM (
+        SELECT
+            *,
+            MIN(timestamp) OVER (PARTITION BY session_id, stint) as stint_start
+        FROM (
+            SELECT
+                *,
+                SUM(CASE WHEN follows AND NOT new_stint THEN 0 ELSE 1 END) OVER (
+                    PARTITION BY session_id ORDER BY timestamp, id ROWS UNBOUNDED PRECEDING
+                ) as stint
+            FROM (
+                SELECT
+                    *,
+                    COALESCE(previous_duration IS NOT NULL
+                        AND (julianday(timestamp) - julianday(previous_timestamp)) * 86400 <= previous_duration + 2, 0) as follows,
+                    NOT (app_ref IS previous_app_ref AND app_id IS previous_app_id) as new_stint
+                FROM (
+                    SELECT
+                        id,
+                        session_id,
+                        app_id,
+                        app_ref,
+                        timestamp,
+                        LAG(app_id) OVER focus_order as previous_app_id,
+                        LAG(app_ref) OVER focus_order as previous_app_ref,
+                        LAG(timestamp) OVER focus_order as previous_timestamp,
+                        LAG(duration) OVER focus_order as previous_duration
+                    FROM window_activities
+                    WHERE event_type = 'focus'
+                    WINDOW focus_order AS (PARTITION BY session_id ORDER BY timestamp, id)
+                )
+            )
+        )
+    )
+    WINDOW focus_order AS (PARTITION BY session_id ORDER BY timestamp, id)
+)
+WHERE follows;
+
+CREATE INDEX IF NOT EXISTS idx_app_transitions_timestamp ON app_transitions(timestamp);
+CREATE INDEX IF NOT EXISTS idx_app_transitions_session_id ON app_transitions(session_id);
@@ -3,6 +3,7 @@ use std::time::Duration;
 use std::path::PathBuf;
 use sha2::{Sha256, Digest};
 
+use crate::data_layout::DataLayout;
 use crate::models::*;
 
 pub struct CacheManager {
@@ -13,12 +14,7 @@ pub struct CacheManager {
 }
 
 impl CacheManager {
-    pub fn new() -> Self {
-        let icon_cache_dir = Self::get_icon_cache_dir().unwrap_or_else(|e| {
-            log::warn!("Failed to create icon cache directory: {}", e);
-            std::env::temp_dir().join("soham_icons")
-        });
-        
+    pub fn new(icon_cache_dir: PathBuf) -> Self {
         // Ensure cache directory exists
         if let Err(e) = std::fs::create_dir_all(&icon_cache_dir) {
             log::warn!("Failed to create icon cache directory: {}", e);
@@ -41,14 +37,6 @@ impl CacheManager {
         }
     }
     
-    fn get_icon_cache_dir() -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
-        let data_dir = dirs::data_dir()
-            .or_else(|| dirs::home_dir().map(|h| h.join(".local/share")))
-            .ok_or("Could not determine data directory")?;
-        
-        Ok(data_dir.join("soham").join("icon_cache"))
-    }
-
     pub async fn get_icon(&self, app_id: &str) -> Option<String> {
         // First check memory cache
         if let Some(icon_data) = self.icon_cache.get(app_id).await {
@@ -133,6 +121,6 @@ impl CacheManager {
 
 impl Default for CacheManager {
     fn default() -> Self {
-        Self::new()
+        Self::new(DataLayout::default().icons_dir())
     }
 }
\ No newline at end of file
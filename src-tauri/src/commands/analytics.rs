@@ -18,6 +18,56 @@ pub async fn get_app_stats(
     state.repository.get_app_stats(from_dt, to_dt).await.map_err(|e| e.to_string())
 }
 
+/// The `apps` registry: every application seen, with its stable identity.
+#[tauri::command]
+pub async fn get_apps(state: State<'_, AppState>) -> Result<Vec<App>, String> {
+    state.repository.get_apps().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_fullscreen_usage(
+    from: i64,
+    to: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<FullscreenUsage>, String> {
+    let from_dt = DateTime::from_timestamp(from, 0)
+        .ok_or_else(|| "Invalid from timestamp".to_string())?;
+    let to_dt = DateTime::from_timestamp(to, 0)
+        .ok_or_else(|| "Invalid to timestamp".to_string())?;
+
+    state.repository.get_fullscreen_usage(from_dt, to_dt).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_monitor_usage(
+    from: i64,
+    to: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<MonitorUsage>, String> {
+    let from_dt = DateTime::from_timestamp(from, 0)
+        .ok_or_else(|| "Invalid from timestamp".to_string())?;
+    let to_dt = DateTime::from_timestamp(to, 0)
+        .ok_or_else(|| "Invalid to timestamp".to_string())?;
+
+    state.repository.get_monitor_usage(from_dt, to_dt).await.map_err(|e| e.to_string())
+}
+
+/// App-to-app switches in the range: how often each pair interrupts the other, the
+/// average time spent before switching and the most common chains.
+#[tauri::command]
+pub async fn get_transition_graph(
+    from: i64,
+    to: i64,
+    state: State<'_, AppState>,
+) -> Result<TransitionGraph, String> {
+    let from_dt = DateTime::from_timestamp(from, 0)
+        .ok_or_else(|| "Invalid from timestamp".to_string())?;
+    let to_dt = DateTime::from_timestamp(to, 0)
+        .ok_or_else(|| "Invalid to timestamp".to_string())?;
+
+    state.repository.get_transition_graph(from_dt, to_dt, 10).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_heatmap_data(
     from: i64,
@@ -54,7 +104,7 @@ pub async fn get_app_lifecycle_events(
     app_id: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<AppLifecycleFlow>, String> {
-    state.repository.get_app_lifecycle_flow(&app_id).await.map_err(|e| e.to_string())
+    state.repository.get_app_lifecycle_events(&app_id).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -85,4 +135,18 @@ pub async fn get_unified_timeline_events_for_session(
     state: State<'_, AppState>,
 ) -> Result<Vec<TimelineEvent>, String> {
     state.repository.get_unified_timeline_events_for_session(session_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_activity_intervals(
+    from: i64,
+    to: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<ActivityInterval>, String> {
+    let from_dt = DateTime::from_timestamp(from, 0)
+        .ok_or_else(|| "Invalid from timestamp".to_string())?;
+    let to_dt = DateTime::from_timestamp(to, 0)
+        .ok_or_else(|| "Invalid to timestamp".to_string())?;
+
+    state.repository.get_activity_intervals(from_dt, to_dt).await.map_err(|e| e.to_string())
 }
\ No newline at end of file
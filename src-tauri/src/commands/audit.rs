@@ -0,0 +1,22 @@
+use tauri::State;
+
+use crate::models::*;
+use crate::state::AppState;
+
+#[tauri::command]
+pub async fn get_audit_events(
+    page: Option<i64>,
+    page_size: Option<i64>,
+    level: Option<String>,
+    kind: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AuditEventPage, String> {
+    let level = level.map(|l| l.parse::<AuditLevel>()).transpose().map_err(|e| e.to_string())?;
+    let kind = kind.map(|k| k.parse::<AuditKind>()).transpose().map_err(|e| e.to_string())?;
+
+    state
+        .audit
+        .get_events(page.unwrap_or(1), page_size.unwrap_or(0), level, kind)
+        .await
+        .map_err(|e| e.to_string())
+}
\ No newline at end of file
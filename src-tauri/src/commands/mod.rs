@@ -1,11 +1,17 @@
 pub mod dashboard;
 pub mod analytics;
+pub mod audit;
+pub mod redaction;
+pub mod retention;
 pub mod screenshots;
 pub mod system;
 pub mod session;
 
 pub use dashboard::*;
 pub use analytics::*;
+pub use audit::*;
+pub use redaction::*;
+pub use retention::*;
 pub use screenshots::*;
 pub use system::*;
 pub use session::*;
\ No newline at end of file
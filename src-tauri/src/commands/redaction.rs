@@ -0,0 +1,37 @@
+use chrono::DateTime;
+use tauri::State;
+
+use crate::models::{HistoryRedactionReport, RedactHistoryRequest};
+use crate::services::HistoryRedaction;
+use crate::state::AppState;
+
+/// Rewrites stored window titles matching `pattern` between `from` and `to`
+/// (unix seconds, both optional). `dry_run` only previews the matches.
+#[tauri::command]
+pub async fn redact_history(
+    pattern: String,
+    replacement: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    delete_screenshots: Option<bool>,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<HistoryRedactionReport, String> {
+    let from = from
+        .map(|from| DateTime::from_timestamp(from, 0).ok_or_else(|| "Invalid from timestamp".to_string()))
+        .transpose()?;
+    let to = to
+        .map(|to| DateTime::from_timestamp(to, 0).ok_or_else(|| "Invalid to timestamp".to_string()))
+        .transpose()?;
+
+    let request = RedactHistoryRequest {
+        pattern,
+        replacement,
+        from,
+        to,
+        delete_screenshots: delete_screenshots.unwrap_or(false),
+        dry_run: dry_run.unwrap_or(false),
+    };
+
+    HistoryRedaction::run(state.inner(), &request).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,13 @@
+use tauri::{AppHandle, State};
+
+use crate::models::RetentionReport;
+use crate::services::RetentionService;
+use crate::state::AppState;
+
+#[tauri::command]
+pub async fn run_retention(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RetentionReport, String> {
+    RetentionService::run(&app_handle, state.inner()).await.map_err(|e| e.to_string())
+}
\ No newline at end of file
@@ -1,6 +1,8 @@
-use tauri::State;
+use chrono::{DateTime, Utc};
+use tauri::{AppHandle, State};
 
-use crate::models::SystemStats;
+use crate::models::{ResumeReason, SystemStats, WriterMetrics};
+use crate::services::PauseService;
 use crate::state::AppState;
 
 #[tauri::command]
@@ -91,24 +93,57 @@ async fn extract_app_icon_base64(app_id: &str) -> Result<String, crate::error::A
 #[derive(serde::Serialize)]
 pub struct AppStatus {
     pub paused: bool,
+    pub paused_until: Option<DateTime<Utc>>,
+    pub remaining_secs: Option<i64>,
+    pub reason: Option<String>,
+    /// Longest pause the policy allows
+    pub max_pause_secs: u64,
 }
 
+/// Pauses tracking for `duration_secs` (default `Config.default_pause_secs`, at most
+/// `Config.max_pause_secs`); it resumes on its own afterwards.
 #[tauri::command]
-pub async fn pause(state: State<'_, AppState>) -> Result<(), String> {
-    state.set_paused(true).await;
-    Ok(())
+pub async fn pause(
+    duration_secs: Option<u64>,
+    reason: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<AppStatus, String> {
+    PauseService::pause(&app_handle, state.inner(), duration_secs, reason)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(app_status(state.inner()).await)
 }
 
 #[tauri::command]
-pub async fn resume(state: State<'_, AppState>) -> Result<(), String> {
-    state.set_paused(false).await;
-    Ok(())
+pub async fn resume(app_handle: AppHandle, state: State<'_, AppState>) -> Result<AppStatus, String> {
+    PauseService::resume(&app_handle, state.inner(), ResumeReason::User)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(app_status(state.inner()).await)
 }
 
 #[tauri::command]
 pub async fn status(state: State<'_, AppState>) -> Result<AppStatus, String> {
+    Ok(app_status(state.inner()).await)
+}
+
+async fn app_status(state: &AppState) -> AppStatus {
     let paused = state.is_paused().await;
-    Ok(AppStatus { paused })
+    let pause = state.pause_state().filter(|_| paused);
+
+    AppStatus {
+        paused,
+        paused_until: pause.as_ref().map(|p| p.until),
+        remaining_secs: pause.as_ref().map(|p| (p.until - Utc::now()).num_seconds().max(0)),
+        reason: pause.and_then(|p| p.reason),
+        max_pause_secs: state.config().max_pause_secs,
+    }
+}
+
+#[tauri::command]
+pub async fn get_config(state: State<'_, AppState>) -> Result<crate::config::Config, String> {
+    Ok(state.config())
 }
 
 #[tauri::command]
@@ -147,4 +182,10 @@ pub async fn get_memory_usage() -> Result<serde_json::Value, String> {
     });
     
     Ok(memory_info)
-}
\ No newline at end of file
+}
+
+/// Queue depth and throughput of the batched activity writer.
+#[tauri::command]
+pub async fn get_writer_metrics(state: State<'_, AppState>) -> Result<WriterMetrics, String> {
+    Ok(state.writer.metrics())
+}
@@ -1,14 +1,160 @@
-use anyhow::Result;
-use directories::ProjectDirs;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::collections::BTreeMap;
+use std::{fs, path::{Path, PathBuf}};
 use toml;
 
+use crate::data_layout::DataLayout;
+use crate::models::ScreenshotFormat;
+use crate::privacy::{SensitiveWindowMatcher, TitleRedactor};
+
 /// Runtime configuration loaded from disk
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub screenshot_interval_secs: u64,
     pub retention_days: u64,
+    /// Seconds without keyboard/mouse input before the user is considered idle
+    pub idle_timeout_secs: u64,
+    /// Length of a pause when the caller doesn't give one
+    pub default_pause_secs: u64,
+    /// Policy limit on a single pause; tracking always resumes by itself
+    pub max_pause_secs: u64,
+    /// Capture each display separately or stitch them into one image
+    pub capture_mode: CaptureMode,
+    /// How captures are scaled and compressed before they are written. The default is
+    /// full-size PNG, as earlier builds wrote; switching to JPEG and a `max_dimension`
+    /// is an explicit choice.
+    pub encoding: EncodingConfig,
+    /// Skip captures while the session is locked or the display is asleep
+    pub skip_when_locked: bool,
+    /// Record an "unchanged" marker instead of a new file when a frame encodes to
+    /// exactly the same bytes as the previous capture of that display
+    pub dedupe: bool,
+    /// Windows that must never appear in a screenshot
+    pub privacy: PrivacyConfig,
+    /// How window titles are scrubbed before they are stored or emitted
+    pub redaction: RedactionConfig,
+    /// Overrides where the database, screenshots and caches live (see `DataLayout`)
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// One image per connected display
+    PerScreen,
+    /// All displays stitched into a single image matching the desktop layout
+    Composite,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodingConfig {
+    pub format: ScreenshotFormat,
+    /// JPEG quality, 1-100 (default 80). PNG and WebP are always lossless, so setting
+    /// it with them is rejected.
+    pub quality: Option<u8>,
+    /// Longest edge in pixels after `scale` is applied; unset keeps the captured size.
+    /// Captures that take over 150 ms to encode are shrunk further until they don't.
+    pub max_dimension: Option<u32>,
+    /// Downscale factor in (0, 1], e.g. 0.5 to undo a 2x HiDPI capture
+    pub scale: f32,
+    pub grayscale: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// App names matched case-insensitively against the focused window's app
+    pub sensitive_apps: Vec<String>,
+    /// Regular expressions matched case-insensitively against the focused window's title
+    pub sensitive_titles: Vec<String>,
+    /// What to do with a capture taken while a sensitive window has focus
+    pub action: SensitiveAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveAction {
+    /// Record a row without an image
+    Skip,
+    /// Black out the window's rectangle; falls back to `Skip` when its geometry is unknown
+    Redact,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    /// Applied in order before the built-in detectors
+    pub rules: Vec<RedactionRule>,
+    pub detect_emails: bool,
+    /// Runs of 32+ hex or base64 characters (API keys, session ids)
+    pub detect_tokens: bool,
+    /// `user:password@` in URLs and secret-looking query parameters
+    pub detect_credential_urls: bool,
+    /// Per-app override keyed by app name (case-insensitive); apps not listed use `Keep`
+    pub app_modes: BTreeMap<String, TitleMode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionRule {
+    /// Regular expression; `replacement` may refer to its groups as `$1`, `${name}`
+    pub pattern: String,
+    #[serde(default = "default_redaction_replacement")]
+    pub replacement: String,
+}
+
+fn default_redaction_replacement() -> String {
+    "[redacted]".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleMode {
+    /// Store the title after redaction rules
+    Keep,
+    /// Store a stable hash so identical titles can still be grouped
+    Hash,
+    /// Store an empty title
+    Drop,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            detect_emails: true,
+            detect_tokens: true,
+            detect_credential_urls: true,
+            app_modes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            sensitive_apps: ["1Password", "Bitwarden", "KeePassXC", "Keychain Access", "LastPass"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            sensitive_titles: Vec::new(),
+            action: SensitiveAction::Redact,
+        }
+    }
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        Self {
+            format: ScreenshotFormat::Png,
+            quality: None,
+            max_dimension: None,
+            scale: 1.0,
+            grayscale: false,
+        }
+    }
 }
 
 impl Default for Config {
@@ -16,30 +162,131 @@ impl Default for Config {
         Self {
             screenshot_interval_secs: 300,
             retention_days: 30,
+            idle_timeout_secs: 180,
+            default_pause_secs: 1800,
+            max_pause_secs: 14_400,
+            capture_mode: CaptureMode::PerScreen,
+            encoding: EncodingConfig::default(),
+            skip_when_locked: true,
+            dedupe: true,
+            privacy: PrivacyConfig::default(),
+            redaction: RedactionConfig::default(),
+            data_dir: None,
         }
     }
 }
 
 impl Config {
-    pub fn data_dir() -> PathBuf {
-        ProjectDirs::from("com", "ExampleCorp", "TrackerAgent")
-            .expect("cannot locate data dir")
-            .data_local_dir()
-            .to_path_buf()
+    pub fn path() -> PathBuf {
+        DataLayout::config_path()
     }
 
-    pub fn path() -> PathBuf {
-        Self::data_dir().join("config.toml")
+    /// The config at `path()`. A file that can't be parsed or fails validation yields
+    /// the defaults along with the error, keeping its `data_dir` when it parsed so
+    /// the same database is opened.
+    pub fn load() -> (Self, Option<anyhow::Error>) {
+        match Self::parse(&Self::path()) {
+            Ok(Some(cfg)) => match cfg.validate() {
+                Ok(()) => (cfg, None),
+                Err(e) => (
+                    Self {
+                        data_dir: cfg.data_dir,
+                        ..Self::default()
+                    },
+                    Some(e),
+                ),
+            },
+            Ok(None) => (Self::default(), None),
+            Err(e) => (Self::default(), Some(e)),
+        }
     }
 
-    pub fn load() -> Result<Self> {
-        let path = Self::path();
-        if path.exists() {
-            let bytes = fs::read(path)?;
-            let cfg: Self = toml::from_str(std::str::from_utf8(&bytes)?)?;
-            Ok(cfg)
-        } else {
-            Ok(Self::default())
+    /// The validated config at `path`, or `None` when there is no file.
+    pub fn read_from(path: &Path) -> Result<Option<Self>> {
+        let cfg = Self::parse(path)?;
+        if let Some(cfg) = &cfg {
+            cfg.validate()?;
         }
+        Ok(cfg)
     }
+
+    fn parse(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(path)?;
+        Ok(Some(toml::from_str(std::str::from_utf8(&bytes)?)?))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(10..=86_400).contains(&self.screenshot_interval_secs) {
+            bail!("screenshot_interval_secs must be between 10 and 86400, got {}", self.screenshot_interval_secs);
+        }
+        if self.retention_days == 0 {
+            bail!("retention_days must be at least 1");
+        }
+        if self.idle_timeout_secs < 10 {
+            bail!("idle_timeout_secs must be at least 10, got {}", self.idle_timeout_secs);
+        }
+        if self.max_pause_secs < 60 {
+            bail!("max_pause_secs must be at least 60, got {}", self.max_pause_secs);
+        }
+        if !(60..=self.max_pause_secs).contains(&self.default_pause_secs) {
+            bail!(
+                "default_pause_secs must be between 60 and max_pause_secs ({}), got {}",
+                self.max_pause_secs,
+                self.default_pause_secs
+            );
+        }
+        match (self.encoding.format, self.encoding.quality) {
+            (ScreenshotFormat::Jpeg, Some(quality)) if !(1..=100).contains(&quality) => {
+                bail!("encoding.quality must be between 1 and 100, got {}", quality);
+            }
+            (ScreenshotFormat::Png | ScreenshotFormat::Webp, Some(_)) => {
+                bail!("encoding.quality only applies to jpeg; {} is always lossless", self.encoding.format.as_str());
+            }
+            _ => {}
+        }
+        if !(self.encoding.scale > 0.0 && self.encoding.scale <= 1.0) {
+            bail!("encoding.scale must be in (0, 1], got {}", self.encoding.scale);
+        }
+        if self.encoding.max_dimension.is_some_and(|max| max < 64) {
+            bail!("encoding.max_dimension must be at least 64");
+        }
+        if let Err(e) = SensitiveWindowMatcher::new(&self.privacy) {
+            bail!("privacy.sensitive_titles: {}", e);
+        }
+        if let Err(e) = TitleRedactor::new(&self.redaction) {
+            bail!("redaction.rules: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Top-level fields whose values differ between `self` and `other`.
+    pub fn diff(&self, other: &Self) -> Vec<ConfigChange> {
+        let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return Vec::new();
+        };
+
+        new.into_iter()
+            .filter_map(|(field, new_value)| {
+                let old_value = old.get(&field).cloned().unwrap_or(serde_json::Value::Null);
+                (old_value != new_value).then_some(ConfigChange {
+                    field,
+                    old: old_value,
+                    new: new_value,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub field: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
 } 
\ No newline at end of file
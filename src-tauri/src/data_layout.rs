@@ -0,0 +1,319 @@
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::database::Repository;
+use crate::error::Result;
+use crate::models::IntegrityStatus;
+
+/// Overrides the data root for both the config file and everything else.
+pub const DATA_DIR_ENV: &str = "SOHAM_DATA_DIR";
+
+const LAYOUT_VERSION_KEY: &str = "data_layout_version";
+const LAYOUT_VERSION: i64 = 1;
+const DATABASE_FILE: &str = "soham.db";
+
+/// Owns every on-disk location the tracker writes to:
+///
+/// ```text
+/// <root>/
+///   config.toml
+///   soham.db
+///   screenshots/YYYY/MM/DD/HHmmss[_s<screen>].{png,jpg,webp}
+///   screenshots/YYYY/MM/DD/HHmmss[_s<screen>].thumb.jpg
+///   icons/
+/// ```
+///
+/// The root is `$SOHAM_DATA_DIR`, else `Config.data_dir`, else the platform data dir.
+/// It is fixed at startup; changing `data_dir` takes effect on the next launch.
+#[derive(Debug, Clone)]
+pub struct DataLayout {
+    root: PathBuf,
+}
+
+impl DataLayout {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Root used before the config is loaded; this is where `config.toml` lives.
+    pub fn bootstrap_root() -> PathBuf {
+        if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            return PathBuf::from(dir);
+        }
+
+        Self::platform_root()
+    }
+
+    fn platform_root() -> PathBuf {
+        dirs::data_dir()
+            .or_else(|| dirs::home_dir().map(|h| h.join(".local/share")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("soham")
+    }
+
+    pub fn config_path() -> PathBuf {
+        Self::bootstrap_root().join("config.toml")
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        if std::env::var_os(DATA_DIR_ENV).is_some_and(|dir| !dir.is_empty()) {
+            return Self::new(Self::bootstrap_root());
+        }
+
+        match &config.data_dir {
+            Some(dir) => Self::new(dir.clone()),
+            None => Self::new(Self::platform_root()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        self.root.join(DATABASE_FILE)
+    }
+
+    pub fn screenshots_dir(&self) -> PathBuf {
+        self.root.join("screenshots")
+    }
+
+    /// `screenshots/YYYY/MM/DD/HHmmss.<ext>` for a capture taken at `timestamp` (UTC),
+    /// or `HHmmss_s<screen>.<ext>` when each display is saved separately.
+    pub fn screenshot_path(&self, timestamp: DateTime<Utc>, screen_id: Option<i64>, extension: &str) -> PathBuf {
+        let name = match screen_id {
+            Some(screen_id) => format!("{}_s{}.{}", timestamp.format("%H%M%S"), screen_id, extension),
+            None => format!("{}.{}", timestamp.format("%H%M%S"), extension),
+        };
+        self.screenshots_dir().join(timestamp.format("%Y/%m/%d").to_string()).join(name)
+    }
+
+    /// `HHmmss[_s<screen>].thumb.jpg` next to the original.
+    pub fn thumbnail_path(original: &Path) -> PathBuf {
+        let stem = original.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        original.with_file_name(format!("{}.thumb.jpg", stem))
+    }
+
+    pub fn icons_dir(&self) -> PathBuf {
+        self.root.join("icons")
+    }
+
+    pub fn ensure(&self) -> std::io::Result<()> {
+        for dir in [self.root.clone(), self.screenshots_dir(), self.icons_dir()] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Moves a config file written by older builds (under the "ExampleCorp/TrackerAgent"
+    /// project dir) to `config_path()`. Runs before the config is loaded.
+    pub fn migrate_legacy_config() {
+        let Some(legacy_dirs) = directories::ProjectDirs::from("com", "ExampleCorp", "TrackerAgent") else {
+            return;
+        };
+
+        let legacy_path = legacy_dirs.data_local_dir().join("config.toml");
+        let new_path = Self::config_path();
+        if !legacy_path.exists() || new_path.exists() {
+            return;
+        }
+
+        if let Some(parent) = new_path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                log::warn!("Failed to create {}: {}", parent.display(), e);
+                return;
+            }
+        }
+
+        match move_file(&legacy_path, &new_path) {
+            Ok(()) => log::info!("Moved config from {} to {}", legacy_path.display(), new_path.display()),
+            Err(e) => log::warn!("Failed to move legacy config {}: {}", legacy_path.display(), e),
+        }
+    }
+
+    /// Moves the database (with its WAL/SHM companions) and icon cache from the
+    /// platform default into a custom root. Runs before the database is opened.
+    pub fn migrate_legacy_files(&self) {
+        let legacy_root = Self::platform_root();
+
+        if legacy_root != self.root && !self.database_path().exists() {
+            for suffix in ["", "-wal", "-shm"] {
+                let from = legacy_root.join(format!("{}{}", DATABASE_FILE, suffix));
+                let to = self.root.join(format!("{}{}", DATABASE_FILE, suffix));
+                if from.exists() {
+                    match move_file(&from, &to) {
+                        Ok(()) => log::info!("Moved {} to {}", from.display(), to.display()),
+                        Err(e) => log::warn!("Failed to move {}: {}", from.display(), e),
+                    }
+                }
+            }
+        }
+
+        let legacy_icons = legacy_root.join("icon_cache");
+        let icons_dir = self.icons_dir();
+        let icons_empty = std::fs::read_dir(&icons_dir).map(|mut d| d.next().is_none()).unwrap_or(true);
+        if legacy_icons.is_dir() && icons_empty {
+            let _ = std::fs::remove_dir(&icons_dir);
+            if let Err(e) = std::fs::rename(&legacy_icons, &icons_dir) {
+                log::warn!("Failed to move icon cache {}: {}", legacy_icons.display(), e);
+            }
+        }
+    }
+
+    /// One-time move of screenshots taken before the layout existed (flat files
+    /// under `~/Documents/Soham/screenshots`) into the dated hierarchy, rewriting
+    /// `screenshots.path` as it goes. Files that are already gone are flagged
+    /// `Missing`; files that fail to move are retried on the next start, and the
+    /// layout version is only recorded once none are left.
+    pub async fn migrate_screenshots(&self, repository: &Repository) -> Result<()> {
+        let version = repository
+            .get_meta(LAYOUT_VERSION_KEY)
+            .await?
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(0);
+        if version >= LAYOUT_VERSION {
+            return Ok(());
+        }
+
+        let screenshots_dir = self.screenshots_dir();
+        let mut moved = 0;
+        let mut remaining = 0;
+        for (id, path, timestamp) in repository.get_screenshot_locations().await? {
+            let current = PathBuf::from(&path);
+            if current.starts_with(&screenshots_dir) {
+                continue;
+            }
+
+            if !current.exists() {
+                log::warn!("Screenshot {} missing at {}, flagging it", id, path);
+                repository
+                    .set_screenshot_integrity(id, IntegrityStatus::Missing, None, Utc::now())
+                    .await?;
+                continue;
+            }
+
+            let extension = current.extension().and_then(|e| e.to_str()).unwrap_or("png");
+            let target = unique_path(self.screenshot_path(timestamp, None, extension));
+            let move_result = tokio::task::spawn_blocking({
+                let current = current.clone();
+                let target = target.clone();
+                move || {
+                    if let Some(parent) = target.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    move_file(&current, &target)
+                }
+            })
+            .await
+            .map_err(|e| crate::error::AppError::FileIO(e.to_string()))?;
+
+            if let Err(e) = move_result {
+                log::warn!("Failed to move screenshot {}: {}", path, e);
+                remaining += 1;
+                continue;
+            }
+
+            repository
+                .update_screenshot_path(id, &target.to_string_lossy())
+                .await?;
+            moved += 1;
+        }
+
+        log::info!("Data layout migration moved {} screenshots", moved);
+        if remaining > 0 {
+            log::warn!("{} screenshots are still outside the data layout, retrying on next start", remaining);
+            return Ok(());
+        }
+        repository.set_meta(LAYOUT_VERSION_KEY, &LAYOUT_VERSION.to_string()).await?;
+        Ok(())
+    }
+}
+
+impl Default for DataLayout {
+    fn default() -> Self {
+        Self::new(Self::bootstrap_root())
+    }
+}
+
+/// Appends `_1`, `_2`, ... to the file stem until the path is free.
+pub fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+    (1..)
+        .map(|n| {
+            let name = match &extension {
+                Some(ext) => format!("{}_{}.{}", stem, n, ext),
+                None => format!("{}_{}", stem, n),
+            };
+            path.with_file_name(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range always yields a free path")
+}
+
+/// Renames, falling back to copy-and-delete across filesystems.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppState;
+
+    async fn insert_screenshot(state: &AppState, path: &str, unchanged_of: Option<i64>) -> i64 {
+        sqlx::query("INSERT INTO screenshots (session_id, path, timestamp, file_size, unchanged_of) VALUES (?, ?, ?, 3, ?)")
+            .bind(state.get_current_session_id().await)
+            .bind(path)
+            .bind("2024-03-05T14:30:00+00:00")
+            .bind(unchanged_of)
+            .execute(state.repository.pool())
+            .await
+            .unwrap()
+            .last_insert_rowid()
+    }
+
+    #[tokio::test]
+    async fn legacy_screenshots_are_moved_and_missing_ones_flagged() {
+        let state = AppState::in_memory().await;
+        let legacy_dir = state.layout.root().join("legacy");
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        let legacy = legacy_dir.join("screenshot_1.png");
+        std::fs::write(&legacy, b"png").unwrap();
+
+        let moved = insert_screenshot(&state, &legacy.to_string_lossy(), None).await;
+        let marker = insert_screenshot(&state, &legacy.to_string_lossy(), Some(moved)).await;
+        let missing = insert_screenshot(&state, &legacy_dir.join("gone.png").to_string_lossy(), None).await;
+        let skipped = insert_screenshot(&state, "", None).await;
+
+        state.layout.migrate_screenshots(&state.repository).await.unwrap();
+
+        let target = state.layout.screenshots_dir().join("2024/03/05/143000.png");
+        assert!(target.exists() && !legacy.exists());
+        let target = target.to_string_lossy().to_string();
+        let rows: Vec<(i64, String, Option<String>)> = sqlx::query_as("SELECT id, path, integrity FROM screenshots ORDER BY id")
+            .fetch_all(state.repository.pool())
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (moved, target.clone(), None),
+                (marker, target, None),
+                (missing, legacy_dir.join("gone.png").to_string_lossy().to_string(), Some("missing".to_string())),
+                (skipped, String::new(), None),
+            ]
+        );
+        assert_eq!(state.repository.get_meta(LAYOUT_VERSION_KEY).await.unwrap(), Some(LAYOUT_VERSION.to_string()));
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::Utc;
+use sqlx::{Pool, Row, Sqlite};
+
+use crate::error::Result;
+use crate::models::{AuditEvent, AuditEventPage, AuditKind, AuditLevel};
+
+use super::repository::parse_timestamp;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// Append-only record of why tracking started, stopped or changed behaviour.
+pub struct AuditLog {
+    pool: Pool<Sqlite>,
+}
+
+impl AuditLog {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        level: AuditLevel,
+        kind: AuditKind,
+        message: &str,
+        metadata: Option<serde_json::Value>,
+    ) -> Result<i64> {
+        let metadata = metadata.map(|m| m.to_string());
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO audit_events (timestamp, level, kind, message, metadata)
+            VALUES (?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(level.as_str())
+        .bind(kind.as_str())
+        .bind(message)
+        .bind(metadata)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Records an event without failing the caller; a broken audit write is logged instead.
+    pub async fn log(&self, level: AuditLevel, kind: AuditKind, message: &str) {
+        if let Err(e) = self.record(level, kind, message, None).await {
+            log::error!("Failed to write audit event ({}): {}", kind.as_str(), e);
+        }
+    }
+
+    pub async fn info(&self, kind: AuditKind, message: &str) {
+        self.log(AuditLevel::Info, kind, message).await;
+    }
+
+    pub async fn warning(&self, kind: AuditKind, message: &str) {
+        self.log(AuditLevel::Warning, kind, message).await;
+    }
+
+    pub async fn error(&self, kind: AuditKind, message: &str) {
+        self.log(AuditLevel::Error, kind, message).await;
+    }
+
+    /// Newest-first page of audit events; `page` starts at 1.
+    pub async fn get_events(
+        &self,
+        page: i64,
+        page_size: i64,
+        level: Option<AuditLevel>,
+        kind: Option<AuditKind>,
+    ) -> Result<AuditEventPage> {
+        let page = page.max(1);
+        let page_size = if page_size > 0 { page_size.min(MAX_PAGE_SIZE) } else { DEFAULT_PAGE_SIZE };
+        let level = level.map(|l| l.as_str());
+        let kind = kind.map(|k| k.as_str());
+
+        let total: i64 = sqlx::query(
+            r#"
+            SELECT COUNT(*) as total
+            FROM audit_events
+            WHERE (? IS NULL OR level = ?) AND (? IS NULL OR kind = ?)
+            "#
+        )
+        .bind(level)
+        .bind(level)
+        .bind(kind)
+        .bind(kind)
+        .fetch_one(&self.pool)
+        .await?
+        .get("total");
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, level, kind, message, metadata
+            FROM audit_events
+            WHERE (? IS NULL OR level = ?) AND (? IS NULL OR kind = ?)
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ? OFFSET ?
+            "#
+        )
+        .bind(level)
+        .bind(level)
+        .bind(kind)
+        .bind(kind)
+        .bind(page_size)
+        .bind((page - 1) * page_size)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let level_str: String = row.get("level");
+            let kind_str: String = row.get("kind");
+            let (Ok(level), Ok(kind)) = (level_str.parse::<AuditLevel>(), kind_str.parse::<AuditKind>()) else {
+                log::warn!("Skipping audit event with unknown level/kind: {}/{}", level_str, kind_str);
+                continue;
+            };
+
+            let metadata_str: Option<String> = row.get("metadata");
+
+            events.push(AuditEvent {
+                id: row.get("id"),
+                timestamp: parse_timestamp(&row.get::<String, _>("timestamp"))?,
+                level,
+                kind,
+                message: row.get("message"),
+                metadata: metadata_str.and_then(|m| serde_json::from_str(&m).ok()),
+            });
+        }
+
+        Ok(AuditEventPage {
+            events,
+            total,
+            page,
+            page_size,
+        })
+    }
+}
+
+impl Clone for AuditLog {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+        }
+    }
+}
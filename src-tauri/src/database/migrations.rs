@@ -0,0 +1,288 @@
+use chrono::Utc;
+use sqlx::{Pool, Row, Sqlite};
+
+use crate::error::{AppError, Result};
+
+/// A single schema change, applied exactly once and in `version` order.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every migration shipped with this build. New schema changes are added as
+/// `migrations/NNN_name.sql` and appended here; existing entries must never be edited.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../../migrations/001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "time_logs",
+        sql: include_str!("../../migrations/002_time_logs.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "audit_events",
+        sql: include_str!("../../migrations/003_audit_events.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "screenshot_screens",
+        sql: include_str!("../../migrations/004_screenshot_screens.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "screenshot_format",
+        sql: include_str!("../../migrations/005_screenshot_format.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "screenshot_integrity",
+        sql: include_str!("../../migrations/006_screenshot_integrity.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "screenshot_thumbnails",
+        sql: include_str!("../../migrations/007_screenshot_thumbnails.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "screenshot_app_index",
+        sql: include_str!("../../migrations/008_screenshot_app_index.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "screenshot_dedupe",
+        sql: include_str!("../../migrations/009_screenshot_dedupe.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "screenshot_privacy",
+        sql: include_str!("../../migrations/010_screenshot_privacy.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "apps",
+        sql: include_str!("../../migrations/011_apps.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "app_transitions",
+        sql: include_str!("../../migrations/012_app_transitions.sql"),
+    },
+];
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Brings the database up to `latest_version()`, refusing to touch a database
+/// that was written by a newer build.
+pub async fn run(pool: &Pool<Sqlite>) -> Result<()> {
+    migrate(pool, MIGRATIONS).await
+}
+
+async fn migrate(pool: &Pool<Sqlite>, migrations: &[Migration]) -> Result<()> {
+    sqlx::raw_sql(
+        r#"
+        CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    let current = current_version(pool).await?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+
+    if current > latest {
+        return Err(AppError::Migration(format!(
+            "Database schema version {} is newer than the latest version {} supported by this build",
+            current, latest
+        )));
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        log::info!("Applying migration {:03}_{}", migration.version, migration.name);
+        apply(pool, migration).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn current_version(pool: &Pool<Sqlite>) -> Result<i64> {
+    let row = sqlx::query("SELECT value FROM meta WHERE key = ?")
+        .bind(SCHEMA_VERSION_KEY)
+        .fetch_optional(pool)
+        .await?;
+
+    match row {
+        Some(row) => {
+            let value: String = row.get("value");
+            value.parse::<i64>().map_err(|e| {
+                AppError::Migration(format!("Invalid schema version '{}': {}", value, e))
+            })
+        }
+        None => Ok(0),
+    }
+}
+
+async fn apply(pool: &Pool<Sqlite>, migration: &Migration) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::raw_sql(migration.sql)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::Migration(format!(
+                "Migration {:03}_{} failed: {}",
+                migration.version, migration.name, e
+            ))
+        })?;
+
+    sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)")
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("INSERT INTO meta (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+        .bind(SCHEMA_VERSION_KEY)
+        .bind(migration.version.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabasePool;
+
+    /// An unmigrated in-memory database; see `DatabasePool::in_memory`.
+    async fn empty_pool() -> Pool<Sqlite> {
+        sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(
+                sqlx::sqlite::SqliteConnectOptions::new()
+                    .filename(":memory:")
+                    .foreign_keys(true)
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn applied(pool: &Pool<Sqlite>) -> Vec<i64> {
+        sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn table_exists(pool: &Pool<Sqlite>, name: &str) -> bool {
+        sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn fresh_database_reaches_latest_version() {
+        let database = DatabasePool::in_memory().await.unwrap();
+        let pool = database.pool();
+
+        assert_eq!(current_version(pool).await.unwrap(), latest_version());
+        assert_eq!(applied(pool).await, MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>());
+        assert!(table_exists(pool, "app_transitions").await);
+    }
+
+    #[tokio::test]
+    async fn rerunning_is_a_no_op() {
+        let database = DatabasePool::in_memory().await.unwrap();
+        let pool = database.pool();
+        let before = applied(pool).await;
+
+        run(pool).await.unwrap();
+        run(pool).await.unwrap();
+
+        assert_eq!(current_version(pool).await.unwrap(), latest_version());
+        assert_eq!(applied(pool).await, before);
+    }
+
+    #[tokio::test]
+    async fn upgrades_a_pre_runner_database() {
+        // Builds before the runner created the initial schema directly and kept no version
+        let pool = empty_pool().await;
+        sqlx::raw_sql(MIGRATIONS[0].sql).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO sessions (start_time) VALUES ('2024-01-01T09:00:00+00:00')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        run(&pool).await.unwrap();
+
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions").fetch_one(&pool).await.unwrap();
+        assert_eq!(sessions, 1);
+        assert!(table_exists(&pool, "time_logs").await);
+    }
+
+    #[tokio::test]
+    async fn refuses_a_newer_database() {
+        let database = DatabasePool::in_memory().await.unwrap();
+        let pool = database.pool();
+        sqlx::query("UPDATE meta SET value = ? WHERE key = ?")
+            .bind((latest_version() + 1).to_string())
+            .bind(SCHEMA_VERSION_KEY)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(run(pool).await, Err(AppError::Migration(_))));
+    }
+
+    #[tokio::test]
+    async fn failing_migration_rolls_back() {
+        let pool = empty_pool().await;
+        let migrations = [
+            Migration {
+                version: 1,
+                name: "first",
+                sql: "CREATE TABLE first (id INTEGER PRIMARY KEY);",
+            },
+            Migration {
+                version: 2,
+                name: "broken",
+                sql: "CREATE TABLE second (id INTEGER PRIMARY KEY); INSERT INTO missing VALUES (1);",
+            },
+        ];
+
+        let result = migrate(&pool, &migrations).await;
+
+        assert!(matches!(result, Err(AppError::Migration(message)) if message.contains("002_broken")));
+        assert_eq!(current_version(&pool).await.unwrap(), 1);
+        assert_eq!(applied(&pool).await, vec![1]);
+        assert!(table_exists(&pool, "first").await);
+        assert!(!table_exists(&pool, "second").await);
+    }
+}
@@ -1,5 +1,8 @@
+pub mod audit;
+pub mod migrations;
 pub mod pool;
 pub mod repository;
 
+pub use audit::AuditLog;
 pub use pool::DatabasePool;
 pub use repository::*;
\ No newline at end of file
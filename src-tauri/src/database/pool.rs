@@ -1,6 +1,8 @@
 use sqlx::{Pool, Sqlite, SqlitePool};
 use crate::error::Result;
 
+use super::migrations;
+
 pub struct DatabasePool {
     pool: Pool<Sqlite>,
 }
@@ -21,7 +23,27 @@ impl DatabasePool {
         .await?;
 
         let database_pool = Self { pool };
-        database_pool.initialize_schema().await?;
+        migrations::run(&database_pool.pool).await?;
+        Ok(database_pool)
+    }
+
+    /// A migrated database that lives as long as the pool. One connection only:
+    /// each connection to `:memory:` would open a database of its own.
+    #[cfg(test)]
+    pub async fn in_memory() -> Result<Self> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(
+                sqlx::sqlite::SqliteConnectOptions::new()
+                    .filename(":memory:")
+                    .foreign_keys(true)
+            )
+            .await?;
+
+        let database_pool = Self { pool };
+        migrations::run(&database_pool.pool).await?;
         Ok(database_pool)
     }
 
@@ -32,72 +54,6 @@ impl DatabasePool {
     pub async fn close(&self) {
         self.pool.close().await;
     }
-
-    async fn initialize_schema(&self) -> Result<()> {
-        // For now, manually create tables instead of using migrations
-        // to avoid the complexity of setting up sqlx-cli
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sessions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                start_time TEXT NOT NULL,
-                end_time TEXT,
-                duration INTEGER,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            );
-            "#
-        ).execute(&self.pool).await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS window_activities (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id INTEGER NOT NULL,
-                app_id TEXT NOT NULL,
-                window_title TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                timestamp TEXT NOT NULL,
-                duration INTEGER,
-                metadata TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-            );
-            "#
-        ).execute(&self.pool).await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS screenshots (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id INTEGER NOT NULL,
-                path TEXT NOT NULL,
-                timestamp TEXT NOT NULL,
-                file_size INTEGER NOT NULL,
-                app_id TEXT,
-                window_title TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-            );
-            "#
-        ).execute(&self.pool).await?;
-
-        // Create indexes
-        let indexes = [
-            "CREATE INDEX IF NOT EXISTS idx_window_activities_timestamp ON window_activities(timestamp);",
-            "CREATE INDEX IF NOT EXISTS idx_window_activities_session_id ON window_activities(session_id);",
-            "CREATE INDEX IF NOT EXISTS idx_window_activities_app_id ON window_activities(app_id);",
-            "CREATE INDEX IF NOT EXISTS idx_window_activities_event_type ON window_activities(event_type);",
-            "CREATE INDEX IF NOT EXISTS idx_screenshots_timestamp ON screenshots(timestamp);",
-            "CREATE INDEX IF NOT EXISTS idx_screenshots_session_id ON screenshots(session_id);",
-            "CREATE INDEX IF NOT EXISTS idx_sessions_start_time ON sessions(start_time);",
-        ];
-
-        for index_sql in &indexes {
-            sqlx::query(index_sql).execute(&self.pool).await?;
-        }
-
-        Ok(())
-    }
 }
 
 impl Clone for DatabasePool {
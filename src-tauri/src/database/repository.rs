@@ -1,8 +1,10 @@
 use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
 use sqlx::{Pool, Sqlite, Row};
 
 use crate::error::{AppError, Result};
 use crate::models::*;
+use crate::screenshot_encoder::hamming_distance;
 
 pub struct Repository {
     pool: Pool<Sqlite>,
@@ -27,6 +29,34 @@ impl Clone for Repository {
 }
 
 impl Repository {
+    pub async fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT value FROM meta WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.and_then(|row| row.get("value")))
+    }
+
+    pub async fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query("INSERT INTO meta (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .bind(key)
+            .bind(value)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_meta(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM meta WHERE key = ?")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn create_session(&self, start_time: DateTime<Utc>) -> Result<i64> {
         let result = sqlx::query("INSERT INTO sessions (start_time) VALUES (?)")
             .bind(start_time.to_rfc3339())
@@ -36,55 +66,552 @@ impl Repository {
         Ok(result.last_insert_rowid())
     }
 
-    pub async fn end_session(&self, session_id: i64, end_time: DateTime<Utc>) -> Result<()> {
+    pub async fn end_session(&self, session_id: i64, end_time: DateTime<Utc>) -> Result<i64> {
+        let start_time_str: String = sqlx::query("SELECT start_time FROM sessions WHERE id = ?")
+            .bind(session_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get("start_time"))
+            .ok_or_else(|| AppError::NotFound(format!("Session {}", session_id)))?;
+
+        let start_time = parse_timestamp(&start_time_str)?;
+        let duration = (end_time - start_time).num_seconds().max(0);
+
         sqlx::query("UPDATE sessions SET end_time = ?, duration = ? WHERE id = ?")
             .bind(end_time.to_rfc3339())
-            .bind((end_time - Utc::now()).num_seconds())
+            .bind(duration)
             .bind(session_id)
             .execute(&self.pool)
             .await?;
 
+        Ok(duration)
+    }
+
+    /// Closes sessions left open by a crash or power loss, ending each one at the
+    /// last activity, focus interval or screenshot recorded in it.
+    pub async fn recover_orphaned_sessions(&self) -> Result<Vec<RecoveredSession>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                s.id,
+                s.start_time,
+                (SELECT MAX(timestamp) FROM window_activities WHERE session_id = s.id) as last_activity,
+                (SELECT MAX(focus_end) FROM time_logs WHERE session_id = s.id) as last_focus,
+                (SELECT MAX(timestamp) FROM screenshots WHERE session_id = s.id) as last_screenshot
+            FROM sessions s
+            WHERE s.end_time IS NULL
+            ORDER BY s.start_time
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut recovered = Vec::new();
+        for row in rows {
+            let session_id: i64 = row.get("id");
+            let start_time = parse_timestamp(&row.get::<String, _>("start_time"))?;
+
+            let end_time = ["last_activity", "last_focus", "last_screenshot"]
+                .iter()
+                .filter_map(|column| row.try_get::<Option<String>, _>(*column).ok().flatten())
+                .filter_map(|value| parse_timestamp(&value).ok())
+                .fold(start_time, |latest, ts| latest.max(ts));
+
+            let duration = self.end_session(session_id, end_time).await?;
+
+            recovered.push(RecoveredSession {
+                session_id,
+                start_time,
+                end_time,
+                duration,
+            });
+        }
+
+        Ok(recovered)
+    }
+
+    /// Registers `app` (or refreshes its name, paths and `last_seen`) and returns its `apps.id`.
+    ///
+    /// The first time an app shows up with a real identity, the `name:` entry for
+    /// the same name (backfilled from rows recorded before the registry, or seen
+    /// while nothing better was reported) is merged into it, so the app isn't
+    /// counted twice.
+    pub async fn upsert_app(&self, app: &AppIdentity, seen: DateTime<Utc>) -> Result<i64> {
+        let stable_id = app.stable_id();
+        let mut tx = self.pool.begin().await?;
+
+        let id: i64 = sqlx::query_scalar(&format!("{} RETURNING id", UPSERT_APP))
+            .bind(&stable_id)
+            .bind(&app.name)
+            .bind(&app.exe_path)
+            .bind(&app.desktop_entry)
+            .bind(&app.wm_class)
+            .bind(seen.to_rfc3339())
+            .bind(seen.to_rfc3339())
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let name_id = format!("name:{}", app.name);
+        if stable_id != name_id {
+            let named: Option<(i64, String)> = sqlx::query_as("SELECT id, first_seen FROM apps WHERE stable_id = ?")
+                .bind(&name_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            if let Some((named_id, first_seen)) = named {
+                for sql in [
+                    "UPDATE window_activities SET app_ref = ? WHERE app_ref = ?",
+                    "UPDATE time_logs SET app_ref = ? WHERE app_ref = ?",
+                    "UPDATE app_transitions SET from_app_ref = ? WHERE from_app_ref = ?",
+                    "UPDATE app_transitions SET to_app_ref = ? WHERE to_app_ref = ?",
+                ] {
+                    sqlx::query(sql).bind(id).bind(named_id).execute(&mut *tx).await?;
+                }
+                sqlx::query("UPDATE apps SET first_seen = MIN(first_seen, ?) WHERE id = ?")
+                    .bind(first_seen)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM apps WHERE id = ?")
+                    .bind(named_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    pub async fn get_apps(&self) -> Result<Vec<App>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, stable_id, display_name, exe_path, desktop_entry, wm_class, first_seen, last_seen
+            FROM apps
+            ORDER BY last_seen DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(App {
+                    id: row.get("id"),
+                    stable_id: row.get("stable_id"),
+                    display_name: row.get("display_name"),
+                    exe_path: row.get("exe_path"),
+                    desktop_entry: row.get("desktop_entry"),
+                    wm_class: row.get("wm_class"),
+                    first_seen: parse_timestamp(&row.get::<String, _>("first_seen"))?,
+                    last_seen: parse_timestamp(&row.get::<String, _>("last_seen"))?,
+                })
+            })
+            .collect()
+    }
+
+    /// The highest `window_activities.id` ever handed out, including deleted rows.
+    pub async fn last_window_activity_id(&self) -> Result<i64> {
+        let id = sqlx::query_scalar(
+            r#"
+            SELECT MAX(
+                COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'window_activities'), 0),
+                COALESCE((SELECT MAX(id) FROM window_activities), 0)
+            )
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Applies `writes` in order, in one transaction.
+    pub async fn write_activities(&self, writes: &[ActivityWrite]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for write in writes {
+            match write {
+                ActivityWrite::App { app, seen } => {
+                    sqlx::query(UPSERT_APP)
+                        .bind(app.stable_id())
+                        .bind(&app.name)
+                        .bind(&app.exe_path)
+                        .bind(&app.desktop_entry)
+                        .bind(&app.wm_class)
+                        .bind(seen.to_rfc3339())
+                        .bind(seen.to_rfc3339())
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                ActivityWrite::Activity(activity) => {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO window_activities (id, session_id, app_id, app_ref, window_title, event_type, timestamp, duration, metadata)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        "#
+                    )
+                    .bind(activity.id)
+                    .bind(activity.session_id)
+                    .bind(&activity.app_id)
+                    .bind(activity.app_ref)
+                    .bind(&activity.window_title)
+                    .bind(&activity.event_type)
+                    .bind(activity.timestamp.to_rfc3339())
+                    .bind(activity.duration)
+                    .bind(&activity.metadata)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                ActivityWrite::TimeLog(time_log) => {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO time_logs (session_id, activity_id, app_id, app_ref, window_title, focus_start, focus_end, duration)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                        "#
+                    )
+                    .bind(time_log.session_id)
+                    .bind(time_log.activity_id)
+                    .bind(&time_log.app_id)
+                    .bind(time_log.app_ref)
+                    .bind(&time_log.window_title)
+                    .bind(time_log.focus_start.to_rfc3339())
+                    .bind(time_log.focus_end.to_rfc3339())
+                    .bind(time_log.duration)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    // Mirror the duration onto the focus row so the window_activities aggregates see it too
+                    if let Some(activity_id) = time_log.activity_id {
+                        sqlx::query("UPDATE window_activities SET duration = ? WHERE id = ?")
+                            .bind(time_log.duration)
+                            .bind(activity_id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+                ActivityWrite::Transition(transition) => {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO app_transitions (session_id, from_app_id, from_app_ref, to_app_id, to_app_ref, transition_type, timestamp, dwell)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                        "#
+                    )
+                    .bind(transition.session_id)
+                    .bind(&transition.from_app_id)
+                    .bind(transition.from_app_ref)
+                    .bind(&transition.to_app_id)
+                    .bind(transition.to_app_ref)
+                    .bind(&transition.transition_type)
+                    .bind(transition.timestamp.to_rfc3339())
+                    .bind(transition.dwell)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn insert_window_activity(&self, activity: &WindowActivity) -> Result<i64> {
-        let result = sqlx::query(
+    /// Rebuilds contiguous focus intervals from `time_logs`, clipped to the range.
+    /// Back-to-back intervals on the same window (gap of at most a second) are merged.
+    pub async fn get_activity_intervals(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<ActivityInterval>> {
+        let rows = sqlx::query(
             r#"
-            INSERT INTO window_activities (session_id, app_id, window_title, event_type, timestamp, duration, metadata)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            SELECT
+                time_logs.app_id,
+                COALESCE(apps.stable_id, 'name:' || time_logs.app_id) as stable_id,
+                COALESCE(apps.display_name, time_logs.app_id) as display_name,
+                window_title,
+                focus_start,
+                focus_end
+            FROM time_logs
+            LEFT JOIN apps ON apps.id = time_logs.app_ref
+            WHERE focus_end >= ? AND focus_start <= ?
+            ORDER BY focus_start
             "#
         )
-        .bind(activity.session_id)
-        .bind(&activity.app_id)
-        .bind(&activity.window_title)
-        .bind(&activity.event_type)
-        .bind(activity.timestamp.to_rfc3339())
-        .bind(activity.duration)
-        .bind(&activity.metadata)
-        .execute(&self.pool)
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(result.last_insert_rowid())
+        let mut intervals: Vec<ActivityInterval> = Vec::new();
+        for row in rows {
+            let start = parse_timestamp(&row.get::<String, _>("focus_start"))?.max(from);
+            let end = parse_timestamp(&row.get::<String, _>("focus_end"))?.min(to);
+            if end <= start {
+                continue;
+            }
+
+            let stable_id: String = row.get("stable_id");
+            let window_title: String = row.get("window_title");
+
+            if let Some(last) = intervals.last_mut() {
+                if last.stable_id == stable_id
+                    && last.window_title == window_title
+                    && start - last.end <= chrono::Duration::seconds(1)
+                {
+                    last.end = last.end.max(end);
+                    last.duration = (last.end - last.start).num_seconds();
+                    continue;
+                }
+            }
+
+            intervals.push(ActivityInterval {
+                app_id: row.get("app_id"),
+                stable_id,
+                display_name: row.get("display_name"),
+                window_title,
+                start,
+                end,
+                duration: (end - start).num_seconds(),
+            });
+        }
+
+        Ok(intervals)
+    }
+
+    /// Per app, how much of the focus time starting in the range was spent fullscreen
+    /// (per the focus event's `WindowMetadata`). Apps never seen fullscreen are left out.
+    pub async fn get_fullscreen_usage(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<FullscreenUsage>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                time_logs.app_id,
+                COALESCE(apps.stable_id, 'name:' || time_logs.app_id) as stable_id,
+                COALESCE(apps.display_name, time_logs.app_id) as display_name,
+                SUM(CASE
+                    WHEN (CASE WHEN json_valid(window_activities.metadata)
+                          THEN json_extract(window_activities.metadata, '$.fullscreen') END) = 1
+                    THEN time_logs.duration ELSE 0
+                END) as fullscreen_duration,
+                SUM(time_logs.duration) as total_duration,
+                MAX(time_logs.focus_start) as last_focused
+            FROM time_logs
+            LEFT JOIN window_activities ON window_activities.id = time_logs.activity_id
+            LEFT JOIN apps ON apps.id = time_logs.app_ref
+            WHERE time_logs.focus_start BETWEEN ? AND ?
+            GROUP BY COALESCE(apps.stable_id, 'name:' || time_logs.app_id)
+            HAVING fullscreen_duration > 0
+            ORDER BY fullscreen_duration DESC
+            "#
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| FullscreenUsage {
+                app_id: row.get("app_id"),
+                stable_id: row.get("stable_id"),
+                display_name: row.get("display_name"),
+                fullscreen_duration: row.get("fullscreen_duration"),
+                total_duration: row.get("total_duration"),
+            })
+            .collect())
+    }
+
+    /// Focus time per app and monitor (`WindowMetadata.monitor`) for intervals starting
+    /// in the range. Intervals whose monitor is unknown are left out.
+    pub async fn get_monitor_usage(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<MonitorUsage>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                time_logs.app_id,
+                COALESCE(apps.stable_id, 'name:' || time_logs.app_id) as stable_id,
+                COALESCE(apps.display_name, time_logs.app_id) as display_name,
+                CASE WHEN json_valid(window_activities.metadata)
+                     THEN json_extract(window_activities.metadata, '$.monitor') END as monitor,
+                SUM(time_logs.duration) as duration,
+                MAX(time_logs.focus_start) as last_focused
+            FROM time_logs
+            JOIN window_activities ON window_activities.id = time_logs.activity_id
+            LEFT JOIN apps ON apps.id = time_logs.app_ref
+            WHERE time_logs.focus_start BETWEEN ? AND ?
+            GROUP BY COALESCE(apps.stable_id, 'name:' || time_logs.app_id), monitor
+            HAVING monitor IS NOT NULL
+            ORDER BY duration DESC
+            "#
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| MonitorUsage {
+                app_id: row.get("app_id"),
+                stable_id: row.get("stable_id"),
+                display_name: row.get("display_name"),
+                monitor: row.get("monitor"),
+                duration: row.get("duration"),
+            })
+            .collect())
+    }
+
+    /// App-to-app switches in the range as a weighted directed graph, with the
+    /// `chain_limit` most frequent three-app chains.
+    pub async fn get_transition_graph(&self, from: DateTime<Utc>, to: DateTime<Utc>, chain_limit: i64) -> Result<TransitionGraph> {
+        let (from, to) = (from.to_rfc3339(), to.to_rfc3339());
+
+        let edges: Vec<TransitionEdge> = sqlx::query(&format!(
+            r#"
+            {}
+            SELECT
+                from_id,
+                MIN(from_name) as from_display_name,
+                to_id,
+                MIN(to_name) as to_display_name,
+                COUNT(*) as count,
+                AVG(dwell) as average_dwell
+            FROM transitions
+            WHERE from_id != to_id
+            GROUP BY from_id, to_id
+            ORDER BY count DESC
+            "#,
+            TRANSITIONS_CTE
+        ))
+        .bind(&from)
+        .bind(&to)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| TransitionEdge {
+            from_stable_id: row.get("from_id"),
+            from_display_name: row.get("from_display_name"),
+            to_stable_id: row.get("to_id"),
+            to_display_name: row.get("to_display_name"),
+            count: row.get("count"),
+            average_dwell: row.get("average_dwell"),
+        })
+        .collect();
+
+        // Consecutive app switches within a session, joined where one ends in the app the next leaves
+        let chains = sqlx::query(&format!(
+            r#"
+            {},
+            app_switches AS (
+                SELECT
+                    *,
+                    LAG(from_id) OVER switch_order as first_id,
+                    LAG(from_name) OVER switch_order as first_name,
+                    LAG(to_id) OVER switch_order as previous_to_id
+                FROM transitions
+                WHERE from_id != to_id
+                WINDOW switch_order AS (PARTITION BY session_id ORDER BY timestamp, id)
+            )
+            SELECT
+                first_id,
+                MIN(first_name) as first_display_name,
+                from_id,
+                MIN(from_name) as from_display_name,
+                to_id,
+                MIN(to_name) as to_display_name,
+                COUNT(*) as count
+            FROM app_switches
+            WHERE previous_to_id = from_id
+            GROUP BY first_id, from_id, to_id
+            ORDER BY count DESC
+            LIMIT ?
+            "#,
+            TRANSITIONS_CTE
+        ))
+        .bind(&from)
+        .bind(&to)
+        .bind(chain_limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| TransitionChain {
+            stable_ids: vec![row.get("first_id"), row.get("from_id"), row.get("to_id")],
+            display_names: vec![
+                row.get("first_display_name"),
+                row.get("from_display_name"),
+                row.get("to_display_name"),
+            ],
+            count: row.get("count"),
+        })
+        .collect();
+
+        let transition_counts = sqlx::query(
+            r#"
+            SELECT transition_type, COUNT(*) as count
+            FROM app_transitions
+            WHERE timestamp BETWEEN ? AND ?
+            GROUP BY transition_type
+            "#
+        )
+        .bind(&from)
+        .bind(&to)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| (row.get("transition_type"), row.get("count")))
+        .collect();
+
+        let mut nodes: Vec<TransitionNode> = Vec::new();
+        for edge in &edges {
+            for (stable_id, display_name, outgoing) in [
+                (&edge.from_stable_id, &edge.from_display_name, true),
+                (&edge.to_stable_id, &edge.to_display_name, false),
+            ] {
+                let index = match nodes.iter().position(|node| &node.stable_id == stable_id) {
+                    Some(index) => index,
+                    None => {
+                        nodes.push(TransitionNode {
+                            stable_id: stable_id.clone(),
+                            display_name: display_name.clone(),
+                            switches_in: 0,
+                            switches_out: 0,
+                        });
+                        nodes.len() - 1
+                    }
+                };
+                if outgoing {
+                    nodes[index].switches_out += edge.count;
+                } else {
+                    nodes[index].switches_in += edge.count;
+                }
+            }
+        }
+
+        Ok(TransitionGraph {
+            nodes,
+            edges,
+            chains,
+            transition_counts,
+        })
     }
 
     pub async fn get_app_stats(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<AppStats>> {
         let rows = sqlx::query(
             r#"
             SELECT 
-                app_id,
+                window_activities.app_id,
+                COALESCE(apps.stable_id, 'name:' || window_activities.app_id) as stable_id,
+                COALESCE(apps.display_name, window_activities.app_id) as display_name,
                 SUM(COALESCE(duration, 0)) as total_duration,
                 COUNT(DISTINCT session_id) as session_count,
                 MAX(timestamp) as last_used,
                 CASE 
-                    WHEN (SELECT SUM(COALESCE(duration, 0)) FROM window_activities WHERE timestamp BETWEEN ? AND ?) > 0 
+                    WHEN (SELECT SUM(COALESCE(duration, 0)) FROM window_activities WHERE timestamp BETWEEN ? AND ? AND event_type IN ('focus', 'blur')) > 0 
                     THEN (SUM(COALESCE(duration, 0)) * 100.0 / 
-                         (SELECT SUM(COALESCE(duration, 0)) FROM window_activities WHERE timestamp BETWEEN ? AND ?))
+                         (SELECT SUM(COALESCE(duration, 0)) FROM window_activities WHERE timestamp BETWEEN ? AND ? AND event_type IN ('focus', 'blur')))
                     ELSE 0.0
                 END as percentage,
                 COUNT(*) as window_count
             FROM window_activities 
-            WHERE timestamp BETWEEN ? AND ?
-            GROUP BY app_id
+            LEFT JOIN apps ON apps.id = window_activities.app_ref
+            -- Lifecycle and idle rows aren't focus time
+            WHERE timestamp BETWEEN ? AND ? AND event_type IN ('focus', 'blur')
+            -- One entry per stable identity; the bare app_id comes from the MAX(timestamp) row
+            GROUP BY COALESCE(apps.stable_id, 'name:' || window_activities.app_id)
             ORDER BY total_duration DESC
             "#
         )
@@ -109,6 +636,8 @@ impl Repository {
 
             stats.push(AppStats {
                 app_id: row.get("app_id"),
+                stable_id: row.get("stable_id"),
+                display_name: row.get("display_name"),
                 total_duration: row.get::<i64, _>("total_duration"),
                 session_count: row.get::<i64, _>("session_count"),
                 last_used,
@@ -129,7 +658,7 @@ impl Repository {
                 COUNT(*) as activity_count,
                 SUM(COALESCE(duration, 0)) as duration
             FROM window_activities 
-            WHERE timestamp BETWEEN ? AND ?
+            WHERE timestamp BETWEEN ? AND ? AND event_type IN ('focus', 'blur')
             GROUP BY DATE(timestamp), strftime('%H', timestamp)
             ORDER BY date, hour
             "#
@@ -162,7 +691,7 @@ impl Repository {
                 COUNT(*) as activity_count,
                 SUM(COALESCE(duration, 0)) as duration
             FROM window_activities 
-            WHERE timestamp BETWEEN ? AND ?
+            WHERE timestamp BETWEEN ? AND ? AND event_type IN ('focus', 'blur')
             GROUP BY strftime('%Y', timestamp), strftime('%m', timestamp), strftime('%d', timestamp)
             ORDER BY year, month, day
             "#
@@ -195,7 +724,7 @@ impl Repository {
                 COUNT(*) as activity_count,
                 SUM(COALESCE(duration, 0)) as duration
             FROM window_activities 
-            WHERE timestamp BETWEEN ? AND ?
+            WHERE timestamp BETWEEN ? AND ? AND event_type IN ('focus', 'blur')
             GROUP BY strftime('%Y', timestamp), strftime('%m', timestamp)
             ORDER BY year, month
             "#
@@ -221,8 +750,12 @@ impl Repository {
     pub async fn insert_screenshot(&self, screenshot: &Screenshot) -> Result<i64> {
         let result = sqlx::query(
             r#"
-            INSERT INTO screenshots (session_id, path, timestamp, file_size, app_id, window_title)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO screenshots (
+                session_id, path, timestamp, file_size, app_id, window_title,
+                screen_id, screen_x, screen_y, screen_width, screen_height, scale_factor, is_composite,
+                format, checksum, thumbnail_path, phash, unchanged_of, privacy_action, privacy_rule
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(screenshot.session_id)
@@ -231,6 +764,20 @@ impl Repository {
         .bind(screenshot.file_size)
         .bind(&screenshot.app_id)
         .bind(&screenshot.window_title)
+        .bind(screenshot.screen_id)
+        .bind(screenshot.screen_x)
+        .bind(screenshot.screen_y)
+        .bind(screenshot.screen_width)
+        .bind(screenshot.screen_height)
+        .bind(screenshot.scale_factor)
+        .bind(screenshot.is_composite)
+        .bind(&screenshot.format)
+        .bind(&screenshot.checksum)
+        .bind(&screenshot.thumbnail_path)
+        .bind(screenshot.phash)
+        .bind(screenshot.unchanged_of)
+        .bind(&screenshot.privacy_action)
+        .bind(&screenshot.privacy_rule)
         .execute(&self.pool)
         .await?;
 
@@ -238,58 +785,389 @@ impl Repository {
     }
 
     pub async fn get_screenshots_in_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Screenshot>> {
-        let rows = sqlx::query("SELECT * FROM screenshots WHERE timestamp BETWEEN ? AND ? ORDER BY timestamp DESC")
+        let rows = sqlx::query("SELECT * FROM screenshots WHERE timestamp BETWEEN ? AND ? ORDER BY timestamp DESC, screen_id")
             .bind(from.to_rfc3339())
             .bind(to.to_rfc3339())
             .fetch_all(&self.pool)
             .await?;
 
-        let mut screenshots = Vec::new();
-        for row in rows {
-            let timestamp_str: String = row.get("timestamp");
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .map_err(|e| AppError::Database(sqlx::Error::Decode(Box::new(e))))?
-                .with_timezone(&Utc);
+        rows.iter().map(screenshot_from_row).collect()
+    }
 
-            screenshots.push(Screenshot {
-                id: row.get("id"),
-                session_id: row.get("session_id"),
-                path: row.get("path"),
-                timestamp,
-                file_size: row.get("file_size"),
-                app_id: row.try_get("app_id").ok(),
-                window_title: row.try_get("window_title").ok(),
+    /// Screenshots in range, optionally limited to one app and/or a case-insensitive title substring.
+    pub async fn search_screenshots(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        app_id: Option<&str>,
+        title_contains: Option<&str>,
+    ) -> Result<Vec<Screenshot>> {
+        let title_pattern = title_contains.map(like_pattern);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM screenshots
+            WHERE timestamp BETWEEN ? AND ?
+              AND (? IS NULL OR app_id = ?)
+              AND (? IS NULL OR window_title LIKE ? ESCAPE '\')
+            ORDER BY timestamp DESC, screen_id
+            "#
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .bind(app_id)
+        .bind(app_id)
+        .bind(&title_pattern)
+        .bind(&title_pattern)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(screenshot_from_row).collect()
+    }
+
+    /// Number of capture ticks per foreground app, most captured first. Multi-display
+    /// captures count once; screenshots taken without a known app have `app_id: None`.
+    pub async fn get_screenshot_counts_by_app(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AppScreenshotCount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT app_id, COUNT(DISTINCT timestamp) as screenshot_count, MAX(timestamp) as last_captured
+            FROM screenshots
+            WHERE timestamp BETWEEN ? AND ?
+            GROUP BY app_id
+            ORDER BY screenshot_count DESC
+            "#
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut counts = Vec::with_capacity(rows.len());
+        for row in &rows {
+            counts.push(AppScreenshotCount {
+                app_id: optional_column(row, "app_id"),
+                screenshot_count: row.get("screenshot_count"),
+                last_captured: parse_timestamp(&row.get::<String, _>("last_captured"))?,
             });
         }
 
-        Ok(screenshots)
+        Ok(counts)
+    }
+
+    pub async fn get_screenshot(&self, id: i64) -> Result<Option<Screenshot>> {
+        let row = sqlx::query("SELECT * FROM screenshots WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(screenshot_from_row).transpose()
+    }
+
+    pub async fn set_screenshot_thumbnail(&self, id: i64, thumbnail_path: &str) -> Result<()> {
+        sqlx::query("UPDATE screenshots SET thumbnail_path = ? WHERE id = ?")
+            .bind(thumbnail_path)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Screenshots in range bundled per capture tick, newest first, displays in `screen_id` order.
+    pub async fn get_screenshot_groups_in_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ScreenshotGroup>> {
+        Ok(group_screenshots(self.get_screenshots_in_range(from, to).await?))
     }
 
     pub async fn get_recent_screenshots(&self, limit: i64) -> Result<Vec<Screenshot>> {
-        let rows = sqlx::query("SELECT * FROM screenshots ORDER BY timestamp DESC LIMIT ?")
+        let rows = sqlx::query("SELECT * FROM screenshots ORDER BY timestamp DESC, screen_id LIMIT ?")
             .bind(limit)
             .fetch_all(&self.pool)
             .await?;
 
-        let mut screenshots = Vec::new();
+        rows.iter().map(screenshot_from_row).collect()
+    }
+
+    /// Screenshots never verified, or last verified before `verified_before`, oldest check first.
+    pub async fn get_screenshots_due_for_verification(
+        &self,
+        verified_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Screenshot>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM screenshots
+            WHERE (verified_at IS NULL OR verified_at < ?)
+              AND unchanged_of IS NULL
+              AND (privacy_action IS NULL OR privacy_action != 'skipped')
+            ORDER BY verified_at IS NOT NULL, verified_at, timestamp
+            LIMIT ?
+            "#
+        )
+        .bind(verified_before.to_rfc3339())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(screenshot_from_row).collect()
+    }
+
+    /// Records a verification result; `checksum` backfills rows captured before checksums existed.
+    pub async fn set_screenshot_integrity(
+        &self,
+        id: i64,
+        status: IntegrityStatus,
+        checksum: Option<&str>,
+        verified_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE screenshots
+            SET integrity = ?, verified_at = ?, checksum = COALESCE(checksum, ?)
+            WHERE id = ?
+            "#
+        )
+        .bind(status.as_str())
+        .bind(verified_at.to_rfc3339())
+        .bind(checksum)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_screenshots_before(&self, cutoff: DateTime<Utc>) -> Result<Vec<Screenshot>> {
+        let rows = sqlx::query("SELECT * FROM screenshots WHERE timestamp < ? ORDER BY timestamp")
+            .bind(cutoff.to_rfc3339())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(screenshot_from_row).collect()
+    }
+
+    /// Screenshots whose perceptual hash is within `max_distance` bits of `hash`, closest
+    /// first. "Unchanged" markers are left out since they repeat their original.
+    pub async fn find_similar_screenshots(
+        &self,
+        hash: u64,
+        max_distance: u32,
+        limit: usize,
+    ) -> Result<Vec<SimilarScreenshot>> {
+        let rows = sqlx::query("SELECT id, phash FROM screenshots WHERE phash IS NOT NULL AND unchanged_of IS NULL")
+            .fetch_all(&self.pool)
+            .await?;
+
+        // SQLite has no popcount, so the distance is computed here
+        let mut matches: Vec<(i64, u32)> = rows
+            .iter()
+            .map(|row| (row.get::<i64, _>("id"), hamming_distance(row.get::<i64, _>("phash") as u64, hash)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        matches.sort_by_key(|(id, distance)| (*distance, -id));
+        matches.truncate(limit);
+
+        let mut similar = Vec::with_capacity(matches.len());
+        for (id, distance) in matches {
+            if let Some(screenshot) = self.get_screenshot(id).await? {
+                similar.push(SimilarScreenshot { screenshot, distance });
+            }
+        }
+
+        Ok(similar)
+    }
+
+    /// Every file referenced by a screenshot row, thumbnails included.
+    pub async fn get_all_screenshot_paths(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            "SELECT path FROM screenshots UNION ALL SELECT thumbnail_path FROM screenshots WHERE thumbnail_path IS NOT NULL"
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("path")).collect())
+    }
+
+    /// Files written by captures: skipped captures have no path and markers share their original's.
+    pub async fn get_screenshot_locations(&self) -> Result<Vec<(i64, String, DateTime<Utc>)>> {
+        let rows = sqlx::query(
+            "SELECT id, path, timestamp FROM screenshots WHERE path != '' AND unchanged_of IS NULL ORDER BY timestamp"
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut locations = Vec::with_capacity(rows.len());
         for row in rows {
-            let timestamp_str: String = row.get("timestamp");
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .map_err(|e| AppError::Database(sqlx::Error::Decode(Box::new(e))))?
-                .with_timezone(&Utc);
+            let timestamp = parse_timestamp(&row.get::<String, _>("timestamp"))?;
+            locations.push((row.get("id"), row.get("path"), timestamp));
+        }
 
-            screenshots.push(Screenshot {
-                id: row.get("id"),
-                session_id: row.get("session_id"),
-                path: row.get("path"),
-                timestamp,
-                file_size: row.get("file_size"),
-                app_id: row.try_get("app_id").ok(),
-                window_title: row.try_get("window_title").ok(),
-            });
+        Ok(locations)
+    }
+
+    /// Moves a screenshot's file reference, along with its markers'.
+    pub async fn update_screenshot_path(&self, id: i64, path: &str) -> Result<()> {
+        sqlx::query("UPDATE screenshots SET path = ? WHERE id = ? OR unchanged_of = ?")
+            .bind(path)
+            .bind(id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Makes the newest marker of `original_id` taken at or after `kept_from` an
+    /// original in its own right and points the other markers at it, so deleting
+    /// `original_id` leaves it and its files in place. Returns its id, if any.
+    pub async fn promote_unchanged_marker(&self, original_id: i64, kept_from: DateTime<Utc>) -> Result<Option<i64>> {
+        let mut tx = self.pool.begin().await?;
+
+        let newest: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM screenshots
+            WHERE unchanged_of = ? AND timestamp >= ?
+            ORDER BY timestamp DESC, id DESC
+            LIMIT 1
+            "#
+        )
+        .bind(original_id)
+        .bind(kept_from.to_rfc3339())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(newest) = newest {
+            sqlx::query("UPDATE screenshots SET unchanged_of = NULL WHERE id = ?")
+                .bind(newest)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE screenshots SET unchanged_of = ? WHERE unchanged_of = ?")
+                .bind(newest)
+                .bind(original_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(newest)
+    }
+
+    pub async fn delete_screenshots(&self, ids: &[i64]) -> Result<u64> {
+        let mut deleted = 0;
+        for chunk in ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let sql = format!("DELETE FROM screenshots WHERE id IN ({})", placeholders);
+
+            let mut tx = self.pool.begin().await?;
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id);
+            }
+            deleted += query.execute(&mut *tx).await?.rows_affected();
+            tx.commit().await?;
         }
 
-        Ok(screenshots)
+        Ok(deleted)
+    }
+
+    /// `(id, window_title)` of rows after `after_id` with a non-empty title in the
+    /// optional time range, in id order, so callers can page through a whole table.
+    pub async fn get_titles_page(
+        &self,
+        table: TitledTable,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, String)>> {
+        let sql = format!(
+            r#"
+            SELECT id, window_title FROM {table}
+            WHERE id > ?
+              AND window_title IS NOT NULL AND window_title != ''
+              AND (? IS NULL OR {column} >= ?)
+              AND (? IS NULL OR {column} <= ?)
+            ORDER BY id
+            LIMIT ?
+            "#,
+            table = table.as_str(),
+            column = table.timestamp_column()
+        );
+        let from = from.map(|t| t.to_rfc3339());
+        let to = to.map(|t| t.to_rfc3339());
+
+        let rows = sqlx::query(&sql)
+            .bind(after_id)
+            .bind(&from)
+            .bind(&from)
+            .bind(&to)
+            .bind(&to)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("window_title"))).collect())
+    }
+
+    /// Rewrites `window_title` for each `(id, title)` in one transaction.
+    pub async fn update_titles(&self, table: TitledTable, titles: &[(i64, String)]) -> Result<u64> {
+        let sql = format!("UPDATE {} SET window_title = ? WHERE id = ?", table.as_str());
+        let mut tx = self.pool.begin().await?;
+
+        let mut updated = 0;
+        for (id, title) in titles {
+            updated += sqlx::query(&sql)
+                .bind(title)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// Deletes focus events, the transitions between them, time logs and finished
+    /// sessions older than `cutoff`.
+    /// Returns (activities, time logs, sessions) removed.
+    pub async fn delete_activity_before(&self, cutoff: DateTime<Utc>, keep_session_id: i64) -> Result<(u64, u64, u64)> {
+        let cutoff = cutoff.to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        let time_logs = sqlx::query("DELETE FROM time_logs WHERE focus_end < ?")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        sqlx::query("DELETE FROM app_transitions WHERE timestamp < ?")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?;
+
+        let activities = sqlx::query("DELETE FROM window_activities WHERE timestamp < ?")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let sessions = sqlx::query("DELETE FROM sessions WHERE end_time IS NOT NULL AND end_time < ? AND id != ?")
+            .bind(&cutoff)
+            .bind(keep_session_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok((activities, time_logs, sessions))
     }
 
     pub async fn get_sessions_for_date(&self, date: &str) -> Result<Vec<Session>> {
@@ -358,59 +1236,78 @@ impl Repository {
         Ok(sessions)
     }
 
+    /// Launches, exits, window creation and destruction on `date` (`YYYY-MM-DD`).
     pub async fn get_app_lifecycle_flow(&self, date: &str) -> Result<Vec<AppLifecycleFlow>> {
         let rows = sqlx::query(
             r#"
             SELECT 
-                id,
-                app_id,
+                window_activities.id,
+                window_activities.app_id,
+                COALESCE(apps.stable_id, 'name:' || window_activities.app_id) as stable_id,
+                COALESCE(apps.display_name, window_activities.app_id) as display_name,
                 window_title,
                 event_type,
                 timestamp,
                 duration,
-                ROW_NUMBER() OVER (ORDER BY timestamp) as position
+                ROW_NUMBER() OVER (ORDER BY timestamp, window_activities.id) as position
             FROM window_activities
+            LEFT JOIN apps ON apps.id = window_activities.app_ref
             WHERE DATE(timestamp) = ?
-            ORDER BY timestamp
+              AND event_type IN ('app_open', 'app_close', 'window_create', 'window_destroy')
+            ORDER BY timestamp, window_activities.id
             "#
         )
         .bind(date)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut flows = Vec::new();
-        for row in rows {
-            let timestamp_str: String = row.get("timestamp");
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .map_err(|e| AppError::Database(sqlx::Error::Decode(Box::new(e))))?
-                .with_timezone(&Utc);
+        rows.iter().map(lifecycle_flow_from_row).collect()
+    }
 
-            flows.push(AppLifecycleFlow {
-                id: row.get("id"),
-                app_id: row.get("app_id"),
-                window_title: row.get("window_title"),
-                event_type: row.get("event_type"),
+    /// Every lifecycle event of one app, matched by reported name or stable id.
+    pub async fn get_app_lifecycle_events(&self, app: &str) -> Result<Vec<AppLifecycleFlow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT 
+                window_activities.id,
+                window_activities.app_id,
+                COALESCE(apps.stable_id, 'name:' || window_activities.app_id) as stable_id,
+                COALESCE(apps.display_name, window_activities.app_id) as display_name,
+                window_title,
+                event_type,
                 timestamp,
-                duration: row.get("duration"),
-                position: row.get("position"),
-            });
-        }
+                duration,
+                ROW_NUMBER() OVER (ORDER BY timestamp, window_activities.id) as position
+            FROM window_activities
+            LEFT JOIN apps ON apps.id = window_activities.app_ref
+            WHERE (window_activities.app_id = ? OR apps.stable_id = ?)
+              AND event_type IN ('app_open', 'app_close', 'window_create', 'window_destroy')
+            ORDER BY timestamp, window_activities.id
+            "#
+        )
+        .bind(app)
+        .bind(app)
+        .fetch_all(&self.pool)
+        .await?;
 
-        Ok(flows)
+        rows.iter().map(lifecycle_flow_from_row).collect()
     }
 
     pub async fn get_session_flow(&self, session_id: i64) -> Result<Vec<AppLifecycleFlow>> {
         let rows = sqlx::query(
             r#"
             SELECT 
-                id,
-                app_id,
+                window_activities.id,
+                window_activities.app_id,
+                COALESCE(apps.stable_id, 'name:' || window_activities.app_id) as stable_id,
+                COALESCE(apps.display_name, window_activities.app_id) as display_name,
                 window_title,
                 event_type,
                 timestamp,
                 duration,
                 ROW_NUMBER() OVER (ORDER BY timestamp) as position
             FROM window_activities
+            LEFT JOIN apps ON apps.id = window_activities.app_ref
             WHERE session_id = ?
             ORDER BY timestamp
             "#
@@ -419,25 +1316,7 @@ impl Repository {
         .fetch_all(&self.pool)
         .await?;
 
-        let mut flows = Vec::new();
-        for row in rows {
-            let timestamp_str: String = row.get("timestamp");
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .map_err(|e| AppError::Database(sqlx::Error::Decode(Box::new(e))))?
-                .with_timezone(&Utc);
-
-            flows.push(AppLifecycleFlow {
-                id: row.get("id"),
-                app_id: row.get("app_id"),
-                window_title: row.get("window_title"),
-                event_type: row.get("event_type"),
-                timestamp,
-                duration: row.get("duration"),
-                position: row.get("position"),
-            });
-        }
-
-        Ok(flows)
+        rows.iter().map(lifecycle_flow_from_row).collect()
     }
 
     pub async fn get_unified_timeline_events(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<TimelineEvent>> {
@@ -446,10 +1325,13 @@ impl Repository {
             SELECT 
                 timestamp,
                 event_type,
-                app_id,
+                window_activities.app_id,
+                COALESCE(apps.stable_id, 'name:' || window_activities.app_id) as stable_id,
+                COALESCE(apps.display_name, window_activities.app_id) as display_name,
                 window_title,
                 metadata
             FROM window_activities
+            LEFT JOIN apps ON apps.id = window_activities.app_ref
             WHERE timestamp BETWEEN ? AND ?
             ORDER BY timestamp DESC
             "#
@@ -475,6 +1357,8 @@ impl Repository {
                     timestamp,
                     event_type,
                     app_id: row.get("app_id"),
+                    stable_id: row.get("stable_id"),
+                    display_name: row.get("display_name"),
                     window_title: row.get("window_title"),
                     metadata,
                 });
@@ -490,10 +1374,13 @@ impl Repository {
             SELECT 
                 timestamp,
                 event_type,
-                app_id,
+                window_activities.app_id,
+                COALESCE(apps.stable_id, 'name:' || window_activities.app_id) as stable_id,
+                COALESCE(apps.display_name, window_activities.app_id) as display_name,
                 window_title,
                 metadata
             FROM window_activities
+            LEFT JOIN apps ON apps.id = window_activities.app_ref
             WHERE session_id = ?
             ORDER BY timestamp DESC
             "#
@@ -518,6 +1405,8 @@ impl Repository {
                     timestamp,
                     event_type,
                     app_id: row.get("app_id"),
+                    stable_id: row.get("stable_id"),
+                    display_name: row.get("display_name"),
                     window_title: row.get("window_title"),
                     metadata,
                 });
@@ -526,4 +1415,116 @@ impl Repository {
 
         Ok(events)
     }
-}
\ No newline at end of file
+}
+
+/// Registers an app or refreshes its name, paths and `last_seen`.
+const UPSERT_APP: &str = r#"
+    INSERT INTO apps (stable_id, display_name, exe_path, desktop_entry, wm_class, first_seen, last_seen)
+    VALUES (?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT(stable_id) DO UPDATE SET
+        display_name = excluded.display_name,
+        exe_path = COALESCE(excluded.exe_path, apps.exe_path),
+        desktop_entry = COALESCE(excluded.desktop_entry, apps.desktop_entry),
+        wm_class = COALESCE(excluded.wm_class, apps.wm_class),
+        last_seen = MAX(apps.last_seen, excluded.last_seen)
+"#;
+
+/// `app_transitions` in a `BETWEEN ? AND ?` range, with both ends resolved to stable
+/// app ids and display names.
+const TRANSITIONS_CTE: &str = r#"
+    WITH transitions AS (
+        SELECT
+            app_transitions.id,
+            app_transitions.session_id,
+            app_transitions.timestamp,
+            app_transitions.dwell,
+            COALESCE(from_apps.stable_id, 'name:' || app_transitions.from_app_id) as from_id,
+            COALESCE(from_apps.display_name, app_transitions.from_app_id) as from_name,
+            COALESCE(to_apps.stable_id, 'name:' || app_transitions.to_app_id) as to_id,
+            COALESCE(to_apps.display_name, app_transitions.to_app_id) as to_name
+        FROM app_transitions
+        LEFT JOIN apps from_apps ON from_apps.id = app_transitions.from_app_ref
+        LEFT JOIN apps to_apps ON to_apps.id = app_transitions.to_app_ref
+        WHERE app_transitions.timestamp BETWEEN ? AND ?
+    )"#;
+
+pub(crate) fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .map_err(|e| AppError::Database(sqlx::Error::Decode(Box::new(e))))?
+        .with_timezone(&Utc))
+}
+
+fn lifecycle_flow_from_row(row: &SqliteRow) -> Result<AppLifecycleFlow> {
+    Ok(AppLifecycleFlow {
+        id: row.get("id"),
+        app_id: row.get("app_id"),
+        stable_id: row.get("stable_id"),
+        display_name: row.get("display_name"),
+        window_title: row.get("window_title"),
+        event_type: row.get("event_type"),
+        timestamp: parse_timestamp(&row.get::<String, _>("timestamp"))?,
+        duration: row.get("duration"),
+        position: row.get("position"),
+    })
+}
+
+fn screenshot_from_row(row: &SqliteRow) -> Result<Screenshot> {
+    Ok(Screenshot {
+        id: row.get("id"),
+        session_id: row.get("session_id"),
+        path: row.get("path"),
+        timestamp: parse_timestamp(&row.get::<String, _>("timestamp"))?,
+        file_size: row.get("file_size"),
+        app_id: optional_column(row, "app_id"),
+        window_title: optional_column(row, "window_title"),
+        screen_id: optional_column(row, "screen_id"),
+        screen_x: optional_column(row, "screen_x"),
+        screen_y: optional_column(row, "screen_y"),
+        screen_width: optional_column(row, "screen_width"),
+        screen_height: optional_column(row, "screen_height"),
+        scale_factor: optional_column(row, "scale_factor"),
+        is_composite: row.try_get("is_composite").unwrap_or(false),
+        format: optional_column(row, "format"),
+        checksum: optional_column(row, "checksum"),
+        integrity: optional_column(row, "integrity"),
+        thumbnail_path: optional_column(row, "thumbnail_path"),
+        phash: optional_column(row, "phash"),
+        unchanged_of: optional_column(row, "unchanged_of"),
+        privacy_action: optional_column(row, "privacy_action"),
+        privacy_rule: optional_column(row, "privacy_rule"),
+        url: None,
+        thumbnail_url: None,
+    })
+}
+
+/// `%needle%` for `LIKE ... ESCAPE '\'`, with the needle's own wildcards escaped.
+fn like_pattern(needle: &str) -> String {
+    let escaped = needle.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Reads a nullable column; decoding it as the bare type would turn NULL into `""` or `0`.
+fn optional_column<'r, T>(row: &'r SqliteRow, column: &str) -> Option<T>
+where
+    T: sqlx::Decode<'r, Sqlite> + sqlx::Type<Sqlite>,
+{
+    row.try_get::<Option<T>, _>(column).ok().flatten()
+}
+
+/// Groups screenshots sharing a session and timestamp, keeping the input order.
+fn group_screenshots(screenshots: Vec<Screenshot>) -> Vec<ScreenshotGroup> {
+    let mut groups: Vec<ScreenshotGroup> = Vec::new();
+    for screenshot in screenshots {
+        match groups.last_mut() {
+            Some(group) if group.timestamp == screenshot.timestamp && group.session_id == screenshot.session_id => {
+                group.screenshots.push(screenshot);
+            }
+            _ => groups.push(ScreenshotGroup {
+                timestamp: screenshot.timestamp,
+                session_id: screenshot.session_id,
+                screenshots: vec![screenshot],
+            }),
+        }
+    }
+    groups
+}
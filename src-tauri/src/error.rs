@@ -5,6 +5,9 @@ pub enum AppError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
     
+    #[error("Migration error: {0}")]
+    Migration(String),
+    
     #[error("Icon extraction error: {0}")]
     IconExtraction(String),
     
@@ -37,6 +40,9 @@ pub enum AppError {
     
     #[error("Image processing error: {0}")]
     ImageProcessing(String),
+
+    #[error("Activity writer error: {0}")]
+    Writer(String),
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -1,19 +1,29 @@
 use chrono::Utc;
-use tauri::{Builder, Manager};
+use std::sync::Arc;
+use tauri::{Builder, Manager, RunEvent};
 
 mod cache;
 mod commands;
 mod config;
+mod data_layout;
 mod database;
 mod error;
 mod icon_extractor;
 mod models;
+mod privacy;
+mod protocol;
+mod screenshot_encoder;
 mod services;
 mod state;
 
 use config::Config;
-use database::DatabasePool;
-use services::{EventMonitor, ScreenshotService, SystemMonitor};
+use data_layout::DataLayout;
+use database::{AuditLog, DatabasePool};
+use models::{AuditKind, AuditLevel};
+use services::{
+    ConfigWatcher, EventMonitor, IdleMonitor, IntegrityVerifier, LifecycleTracker, PauseService, RetentionService,
+    ScreenshotService, SystemMonitor,
+};
 use state::AppState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -26,13 +36,19 @@ pub fn run() {
             commands::pause,
             commands::resume,
             commands::status,
+            commands::get_config,
             commands::is_app_ready,
             commands::refresh_webview,
             commands::get_memory_usage,
+            commands::get_writer_metrics,
             commands::get_dashboard_data,
             commands::get_heatmap_data,
             commands::get_app_icon,
             commands::get_app_stats,
+            commands::get_apps,
+            commands::get_fullscreen_usage,
+            commands::get_monitor_usage,
+            commands::get_transition_graph,
             commands::get_app_lifecycle_events,
             commands::get_app_lifecycle_flow,
             commands::get_recent_screenshots,
@@ -43,7 +59,21 @@ pub fn run() {
             commands::get_sessions_for_date,
             commands::get_unified_timeline_events_for_session,
             commands::get_screenshots_in_range,
+            commands::get_screenshot_groups,
+            commands::get_screenshot_app_counts,
+            commands::verify_screenshots,
+            commands::find_similar_screenshots,
+            commands::get_activity_intervals,
+            commands::get_audit_events,
+            commands::run_retention,
+            commands::redact_history,
         ])
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, |ctx, request, responder| {
+            let app_handle = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(protocol::handle(&app_handle, request).await);
+            });
+        })
         .setup(|app| {
             log::info!("🔧 Setting up Tauri application...");
             
@@ -65,6 +95,8 @@ pub fn run() {
                 }
             });
             
+            install_crash_audit(app_state.audit.clone());
+
             // Clone state for background services
             let state_for_services = app_state.clone();
             
@@ -81,34 +113,102 @@ pub fn run() {
             
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // ExitRequested fires when the last window is closed, Exit on any other way out;
+            // whichever comes first closes the session.
+            if matches!(event, RunEvent::ExitRequested { .. } | RunEvent::Exit) {
+                if let Some(state) = app_handle.try_state::<AppState>() {
+                    if state.begin_shutdown() {
+                        tauri::async_runtime::block_on(shutdown(state.inner().clone()));
+                    }
+                }
+            }
+        });
 }
 
 async fn initialize_app_state() -> Result<AppState, Box<dyn std::error::Error + Send + Sync>> {
     log::info!("📋 Loading configuration...");
-    let config = Config::load()?;
-    log::info!("✅ Configuration loaded");
+    DataLayout::migrate_legacy_config();
+    let (config, config_error) = Config::load();
+    match &config_error {
+        None => log::info!("✅ Configuration loaded"),
+        Some(e) => log::error!("❌ Invalid configuration {}, using defaults: {}", Config::path().display(), e),
+    }
+
+    let layout = DataLayout::from_config(&config);
+    layout.ensure()?;
+    layout.migrate_legacy_files();
+    log::info!("📁 Data directory: {}", layout.root().display());
 
     // Request macOS permissions early
     #[cfg(target_os = "macos")]
     request_macos_permissions();
 
     log::info!("🗄️ Initializing database...");
-    let db_path = get_database_path()?;
+    let db_path = layout.database_path().to_string_lossy().to_string();
     log::info!("📍 Database path: {}", db_path);
     
     let db_pool = DatabasePool::new(&db_path).await?;
     log::info!("✅ Database initialized");
 
     log::info!("🏗️ Setting up application state...");
-    let app_state = AppState::new(db_pool);
+    let app_state = AppState::new(db_pool, config, layout);
+
+    log::info!("📦 Migrating screenshots into the data layout...");
+    app_state.layout.migrate_screenshots(&app_state.repository).await?;
+
+    log::info!("⏸️ Restoring pause state...");
+    PauseService::restore(&app_state).await?;
+
+    log::info!("🩹 Checking for sessions left open by an unclean exit...");
+    for recovered in app_state.repository.recover_orphaned_sessions().await? {
+        log::warn!(
+            "⚠️ Recovered session {} (ended at {})",
+            recovered.session_id,
+            recovered.end_time.to_rfc3339()
+        );
+
+        if let Err(e) = app_state
+            .audit
+            .record(
+                AuditLevel::Warning,
+                AuditKind::Recovery,
+                &format!("Closed session {} left open by an unclean exit", recovered.session_id),
+                serde_json::to_value(&recovered).ok(),
+            )
+            .await
+        {
+            log::error!("❌ Failed to audit session recovery: {}", e);
+        }
+    }
+
+    if let Some(e) = config_error {
+        if let Err(audit_err) = app_state
+            .audit
+            .record(
+                AuditLevel::Error,
+                AuditKind::ConfigChange,
+                &format!("Invalid configuration, started with defaults: {}", e),
+                None,
+            )
+            .await
+        {
+            log::error!("❌ Failed to audit invalid config: {}", audit_err);
+        }
+    }
 
     log::info!("🆔 Creating session...");
     let session_id = app_state.repository.create_session(Utc::now()).await?;
     app_state.set_current_session_id(session_id).await;
     log::info!("✅ Session created with ID: {}", session_id);
 
+    app_state
+        .audit
+        .info(AuditKind::Start, &format!("Tracker started (session {})", session_id))
+        .await;
+
     Ok(app_state)
 }
 
@@ -116,28 +216,52 @@ async fn start_background_services(
     app_handle: tauri::AppHandle,
     app_state: AppState,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    log::info!("📋 Loading configuration for services...");
-    let config = Config::load()?;
-    
     log::info!("🔄 Starting background services...");
+
+    // Start the activity writer before anything queues rows for it
+    app_state.writer.spawn(app_handle.clone(), Arc::clone(&app_state.cache));
+    log::info!("✅ Activity writer started");
+
+    // Start config watcher
+    ConfigWatcher::spawn(app_handle.clone(), app_state.clone());
+    log::info!("✅ Config watcher started");
+
+    // Start pause expiry
+    PauseService::spawn(app_handle.clone(), app_state.clone());
+    log::info!("✅ Pause service started");
     
     // Start screenshot service
     ScreenshotService::spawn(
         app_state.repository.clone(),
         app_handle.clone(),
         app_state.clone(),
-        config.screenshot_interval_secs,
     );
     log::info!("✅ Screenshot service started");
 
     // Start event monitor
-    EventMonitor::spawn(
-        app_state.repository.clone(),
-        app_handle.clone(),
-        app_state.clone(),
-    );
+    let window_source = services::window_source::default_window_source();
+    log::info!("🪟 Window source: {}", window_source.name());
+    EventMonitor::spawn(app_state.clone(), window_source);
     log::info!("✅ Event monitor started");
 
+    // Start idle detection
+    match services::idle::default_idle_source() {
+        Some(source) => {
+            IdleMonitor::spawn(app_state.clone(), source);
+            log::info!("✅ Idle monitor started");
+        }
+        None => log::warn!("⚠️ No idle source available on this platform, idle detection disabled"),
+    }
+
+    // Start app and window lifecycle tracking
+    match services::lifecycle::default_lifecycle_source() {
+        Some(source) => {
+            LifecycleTracker::spawn(app_state.clone(), source);
+            log::info!("✅ Lifecycle tracker started");
+        }
+        None => log::warn!("⚠️ No lifecycle source available on this platform, launch/exit tracking disabled"),
+    }
+
     // Start system monitor
     SystemMonitor::spawn(
         app_state.repository.clone(),
@@ -146,19 +270,57 @@ async fn start_background_services(
     );
     log::info!("✅ System monitor started");
 
+    // Start retention enforcement
+    RetentionService::spawn(app_handle.clone(), app_state.clone());
+    log::info!("✅ Retention service started");
+
+    // Start screenshot integrity checks
+    IntegrityVerifier::spawn(app_handle.clone(), app_state.clone());
+    log::info!("✅ Integrity verifier started");
+
     log::info!("🎉 All services started successfully");
     Ok(())
 }
 
-fn get_database_path() -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    let data_dir = dirs::data_dir()
-        .or_else(|| dirs::home_dir().map(|h| h.join(".local/share")))
-        .ok_or("Could not determine data directory")?;
+async fn shutdown(app_state: AppState) {
+    log::info!("🛑 Shutting down Soham Tracker...");
+
+    if let Err(e) = app_state.focus.close(&app_state.writer, Utc::now()).await {
+        log::error!("❌ Failed to persist final focus interval: {}", e);
+    }
+
+    let queued = app_state.writer.metrics().queue_depth;
+    match app_state.writer.flush().await {
+        Ok(()) => log::info!("💾 Flushed {} queued activity writes", queued),
+        Err(e) => log::error!("❌ Failed to flush queued activity writes: {}", e),
+    }
+
+    let session_id = app_state.get_current_session_id().await;
+    match app_state.repository.end_session(session_id, Utc::now()).await {
+        Ok(duration) => log::info!("✅ Session {} closed after {}s", session_id, duration),
+        Err(e) => log::error!("❌ Failed to close session {}: {}", session_id, e),
+    }
+
+    app_state.audit.info(AuditKind::Stop, "Tracker stopped").await;
+
+    log::info!("✅ Shutdown complete");
+}
+
+/// Records panics as `crash` audit events before handing off to the default hook.
+fn install_crash_audit(audit: AuditLog) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let audit = audit.clone();
+        let message = info.to_string();
 
-    let app_data_dir = data_dir.join("soham");
-    std::fs::create_dir_all(&app_data_dir)?;
+        // Write from a fresh thread: the panicking one may already be driving the async runtime
+        let _ = std::thread::spawn(move || {
+            tauri::async_runtime::block_on(audit.log(AuditLevel::Critical, AuditKind::Crash, &message))
+        })
+        .join();
 
-    Ok(app_data_dir.join("soham.db").to_string_lossy().to_string())
+        default_hook(info);
+    }));
 }
 
 #[cfg(target_os = "macos")]
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum EventType {
@@ -10,6 +11,8 @@ pub enum EventType {
     AppClose,
     WindowCreate,
     WindowDestroy,
+    Idle,
+    Active,
 }
 
 impl EventType {
@@ -21,6 +24,8 @@ impl EventType {
             EventType::AppClose => "app_close",
             EventType::WindowCreate => "window_create",
             EventType::WindowDestroy => "window_destroy",
+            EventType::Idle => "idle",
+            EventType::Active => "active",
         }
     }
 }
@@ -36,6 +41,8 @@ impl std::str::FromStr for EventType {
             "app_close" => Ok(EventType::AppClose),
             "window_create" => Ok(EventType::WindowCreate),
             "window_destroy" => Ok(EventType::WindowDestroy),
+            "idle" => Ok(EventType::Idle),
+            "active" => Ok(EventType::Active),
             _ => Err(crate::error::AppError::InvalidInput(format!("Unknown event type: {}", s))),
         }
     }
@@ -60,11 +67,172 @@ impl TransitionType {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditLevel {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl AuditLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditLevel::Info => "info",
+            AuditLevel::Warning => "warning",
+            AuditLevel::Error => "error",
+            AuditLevel::Critical => "critical",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditLevel {
+    type Err = crate::error::AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(AuditLevel::Info),
+            "warning" => Ok(AuditLevel::Warning),
+            "error" => Ok(AuditLevel::Error),
+            "critical" => Ok(AuditLevel::Critical),
+            _ => Err(crate::error::AppError::InvalidInput(format!("Unknown audit level: {}", s))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    Start,
+    Stop,
+    Pause,
+    Resume,
+    ConfigChange,
+    Crash,
+    WatchdogRestart,
+    Recovery,
+    Integrity,
+    Privacy,
+    Redaction,
+}
+
+impl AuditKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditKind::Start => "start",
+            AuditKind::Stop => "stop",
+            AuditKind::Pause => "pause",
+            AuditKind::Resume => "resume",
+            AuditKind::ConfigChange => "config_change",
+            AuditKind::Crash => "crash",
+            AuditKind::WatchdogRestart => "watchdog_restart",
+            AuditKind::Recovery => "recovery",
+            AuditKind::Integrity => "integrity",
+            AuditKind::Privacy => "privacy",
+            AuditKind::Redaction => "redaction",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditKind {
+    type Err = crate::error::AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" => Ok(AuditKind::Start),
+            "stop" => Ok(AuditKind::Stop),
+            "pause" => Ok(AuditKind::Pause),
+            "resume" => Ok(AuditKind::Resume),
+            "config_change" => Ok(AuditKind::ConfigChange),
+            "crash" => Ok(AuditKind::Crash),
+            "watchdog_restart" => Ok(AuditKind::WatchdogRestart),
+            "recovery" => Ok(AuditKind::Recovery),
+            "integrity" => Ok(AuditKind::Integrity),
+            "privacy" => Ok(AuditKind::Privacy),
+            "redaction" => Ok(AuditKind::Redaction),
+            _ => Err(crate::error::AppError::InvalidInput(format!("Unknown audit kind: {}", s))),
+        }
+    }
+}
+
+/// What the platform reports about an application. `name` is only what the user
+/// sees and may be localized or change between versions; `stable_id` picks the
+/// most durable of the other fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppIdentity {
+    pub name: String,
+    pub exe_path: Option<String>,
+    pub wm_class: Option<String>,
+    /// Desktop file id such as `org.mozilla.firefox.desktop` (Linux)
+    pub desktop_entry: Option<String>,
+}
+
+impl AppIdentity {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// `desktop:<id>`, `exe:<path>`, `wm_class:<class>` or, when nothing better is
+    /// known, `name:<name>`.
+    pub fn stable_id(&self) -> String {
+        if let Some(desktop_entry) = &self.desktop_entry {
+            format!("desktop:{}", desktop_entry)
+        } else if let Some(exe_path) = &self.exe_path {
+            format!("exe:{}", exe_path)
+        } else if let Some(wm_class) = &self.wm_class {
+            format!("wm_class:{}", wm_class)
+        } else {
+            format!("name:{}", self.name)
+        }
+    }
+}
+
+/// Platform details of a focused window, stored as JSON in `window_activities.metadata`.
+/// Every key is always written (`null` when unknown), so queries can rely on
+/// `json_extract(metadata, '$.fullscreen')` and friends.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowMetadata {
+    /// Backend that reported the window (`x11`, `active_win`, `scripted`)
+    pub source: String,
+    pub pid: Option<u32>,
+    pub process_path: Option<String>,
+    pub window_id: Option<String>,
+    /// Window rectangle in desktop coordinates
+    pub x: Option<i64>,
+    pub y: Option<i64>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub fullscreen: Option<bool>,
+    /// Index of the display holding most of the window
+    pub monitor: Option<u32>,
+}
+
+/// A row of the `apps` registry that activities reference through `app_ref`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct App {
+    pub id: i64,
+    pub stable_id: String,
+    /// Most recently reported name
+    pub display_name: String,
+    pub exe_path: Option<String>,
+    pub desktop_entry: Option<String>,
+    pub wm_class: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct WindowActivity {
     pub id: Option<i64>,
     pub session_id: i64,
     pub app_id: String,
+    /// `apps.id`
+    pub app_ref: Option<i64>,
     pub window_title: String,
     pub event_type: String,
     pub timestamp: DateTime<Utc>,
@@ -72,9 +240,35 @@ pub struct WindowActivity {
     pub metadata: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TimeLog {
+    pub id: Option<i64>,
+    pub session_id: i64,
+    pub activity_id: Option<i64>,
+    pub app_id: String,
+    pub app_ref: Option<i64>,
+    pub window_title: String,
+    pub focus_start: DateTime<Utc>,
+    pub focus_end: DateTime<Utc>,
+    pub duration: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityInterval {
+    pub app_id: String,
+    pub stable_id: String,
+    pub display_name: String,
+    pub window_title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct AppStats {
     pub app_id: String,
+    pub stable_id: String,
+    pub display_name: String,
     pub total_duration: i64,
     pub session_count: i64,
     pub last_used: DateTime<Utc>,
@@ -92,6 +286,37 @@ pub struct Session {
     pub screenshot_count: i64,
 }
 
+/// An active pause. Persisted in `meta` so a restart doesn't silently resume tracking.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PauseState {
+    pub started_at: DateTime<Utc>,
+    /// Tracking resumes by itself at this time
+    pub until: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResumeReason {
+    User,
+    Expired,
+}
+
+/// Payload of the `resumed` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumedPayload {
+    pub reason: ResumeReason,
+    pub pause: PauseState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredSession {
+    pub session_id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Screenshot {
     pub id: i64,
@@ -101,6 +326,114 @@ pub struct Screenshot {
     pub file_size: i64,
     pub app_id: Option<String>,
     pub window_title: Option<String>,
+    /// Display the image came from; `None` for composites and rows captured before multi-monitor support
+    pub screen_id: Option<i64>,
+    /// Display geometry in desktop coordinates (logical pixels)
+    pub screen_x: Option<i64>,
+    pub screen_y: Option<i64>,
+    pub screen_width: Option<i64>,
+    pub screen_height: Option<i64>,
+    pub scale_factor: Option<f64>,
+    /// The image is every display stitched together
+    pub is_composite: bool,
+    /// Encoding of the file at `path`; `None` for rows written before it was configurable (PNG)
+    pub format: Option<String>,
+    /// Hex SHA-256 of the file as written at capture
+    pub checksum: Option<String>,
+    /// Result of the last verification (`IntegrityStatus`); `None` until first checked
+    pub integrity: Option<String>,
+    /// Small JPEG written next to the original at capture
+    pub thumbnail_path: Option<String>,
+    /// 64-bit difference hash of the captured frame, stored as its bit pattern
+    pub phash: Option<i64>,
+    /// Set on "unchanged" marker rows: the frame matched this earlier screenshot, whose
+    /// file `path` points at, so no new file was written
+    pub unchanged_of: Option<i64>,
+    /// `PrivacyAction` taken because a sensitive window had focus; `None` for ordinary captures
+    pub privacy_action: Option<String>,
+    /// The `SensitiveMatch` rule that triggered `privacy_action`
+    pub privacy_rule: Option<String>,
+    /// `soham://` URLs the webview loads the images from; filled in by the list commands
+    #[sqlx(skip)]
+    pub url: Option<String>,
+    #[sqlx(skip)]
+    pub thumbnail_url: Option<String>,
+}
+
+impl Screenshot {
+    /// False for captures skipped over a sensitive window, which have no file.
+    pub fn has_image(&self) -> bool {
+        self.privacy_action.as_deref() != Some(PrivacyAction::Skipped.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ScreenshotFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Jpeg => "jpeg",
+            ScreenshotFormat::Webp => "webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Jpeg => "jpg",
+            ScreenshotFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "image/png",
+            ScreenshotFormat::Jpeg => "image/jpeg",
+            ScreenshotFormat::Webp => "image/webp",
+        }
+    }
+}
+
+impl std::str::FromStr for ScreenshotFormat {
+    type Err = crate::error::AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(ScreenshotFormat::Png),
+            "jpeg" | "jpg" => Ok(ScreenshotFormat::Jpeg),
+            "webp" => Ok(ScreenshotFormat::Webp),
+            _ => Err(crate::error::AppError::InvalidInput(format!("Unknown screenshot format: {}", s))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppScreenshotCount {
+    pub app_id: Option<String>,
+    pub screenshot_count: i64,
+    pub last_captured: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarScreenshot {
+    pub screenshot: Screenshot,
+    /// Differing perceptual-hash bits (0-64); lower is more similar
+    pub distance: u32,
+}
+
+/// Every display captured on the same tick.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenshotGroup {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: i64,
+    pub screenshots: Vec<Screenshot>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -132,6 +465,8 @@ pub struct ActivityHeatmapYearData {
 pub struct AppLifecycleFlow {
     pub id: i64,
     pub app_id: String,
+    pub stable_id: String,
+    pub display_name: String,
     pub window_title: String,
     pub event_type: String,
     pub timestamp: DateTime<Utc>,
@@ -139,15 +474,287 @@ pub struct AppLifecycleFlow {
     pub position: Option<i32>,
 }
 
+/// Focus time an app spent fullscreen, out of its total focus time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FullscreenUsage {
+    pub app_id: String,
+    pub stable_id: String,
+    pub display_name: String,
+    pub fullscreen_duration: i64,
+    pub total_duration: i64,
+}
+
+/// Focus time an app spent on one monitor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorUsage {
+    pub app_id: String,
+    pub stable_id: String,
+    pub display_name: String,
+    pub monitor: i64,
+    pub duration: i64,
+}
+
+/// One focus change from one window to the next, stored in `app_transitions`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AppTransition {
+    pub id: Option<i64>,
+    pub session_id: i64,
+    pub from_app_id: String,
+    pub from_app_ref: Option<i64>,
+    pub to_app_id: String,
+    pub to_app_ref: Option<i64>,
+    pub transition_type: String,
+    pub timestamp: DateTime<Utc>,
+    /// Seconds spent in the previous app before switching; for switches within an
+    /// app, in the previous window
+    pub dwell: i64,
+}
+
+/// A row for `ActivityWriter` to apply; a batch is applied in order, in one transaction.
+#[derive(Debug, Clone)]
+pub enum ActivityWrite {
+    /// Refreshes the registry entry (display name, paths, `last_seen`) of a known app
+    App { app: AppIdentity, seen: DateTime<Utc> },
+    /// Inserted with its preallocated `id`
+    Activity(WindowActivity),
+    TimeLog(TimeLog),
+    Transition(AppTransition),
+}
+
+/// Queue and throughput figures of the activity writer.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WriterMetrics {
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    /// Deepest the queue has been since startup
+    pub max_queue_depth: usize,
+    pub written: u64,
+    /// Writes given up on after repeated failures
+    pub failed: u64,
+    /// Failed writes waiting to be retried
+    pub pending_retries: usize,
+    pub batches: u64,
+    pub last_batch_size: usize,
+    /// Milliseconds from queueing the oldest write of the last batch to its commit
+    pub last_write_latency_ms: u64,
+}
+
+/// An app in the transition graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionNode {
+    pub stable_id: String,
+    pub display_name: String,
+    pub switches_in: i64,
+    pub switches_out: i64,
+}
+
+/// Switches from one app to another, weighted by how often they happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionEdge {
+    pub from_stable_id: String,
+    pub from_display_name: String,
+    pub to_stable_id: String,
+    pub to_display_name: String,
+    pub count: i64,
+    /// Average seconds spent in the `from` app before switching
+    pub average_dwell: f64,
+}
+
+/// Three apps visited in a row, e.g. editor -> chat -> editor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionChain {
+    pub stable_ids: Vec<String>,
+    pub display_names: Vec<String>,
+    pub count: i64,
+}
+
+/// Directed app-to-app switch graph for a time range. Window switches within an app
+/// are only counted in `transition_counts`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionGraph {
+    pub nodes: Vec<TransitionNode>,
+    pub edges: Vec<TransitionEdge>,
+    pub chains: Vec<TransitionChain>,
+    /// Transitions of each `TransitionType`, keyed by `TransitionType::as_str`
+    pub transition_counts: HashMap<String, i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimelineEvent {
     pub timestamp: DateTime<Utc>,
     pub event_type: EventType,
     pub app_id: String,
+    pub stable_id: String,
+    pub display_name: String,
     pub window_title: Option<String>,
     pub metadata: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub ran_at: DateTime<Utc>,
+    pub cutoff: DateTime<Utc>,
+    pub screenshots_deleted: u64,
+    pub files_deleted: u64,
+    pub missing_files: u64,
+    pub orphaned_files_deleted: u64,
+    pub bytes_reclaimed: u64,
+    pub activities_deleted: u64,
+    pub time_logs_deleted: u64,
+    pub sessions_deleted: u64,
+}
+
+/// Tables that store window titles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TitledTable {
+    WindowActivities,
+    TimeLogs,
+    Screenshots,
+}
+
+impl TitledTable {
+    pub const ALL: [TitledTable; 3] = [TitledTable::WindowActivities, TitledTable::TimeLogs, TitledTable::Screenshots];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TitledTable::WindowActivities => "window_activities",
+            TitledTable::TimeLogs => "time_logs",
+            TitledTable::Screenshots => "screenshots",
+        }
+    }
+
+    /// Column a time range is matched against
+    pub fn timestamp_column(&self) -> &'static str {
+        match self {
+            TitledTable::WindowActivities | TitledTable::Screenshots => "timestamp",
+            TitledTable::TimeLogs => "focus_start",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactHistoryRequest {
+    /// Regular expression matched against stored window titles
+    pub pattern: String,
+    /// Replacement text; may refer to groups as `$1`, `${name}`
+    pub replacement: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Delete matching screenshots (rows and files) instead of rewriting their titles
+    pub delete_screenshots: bool,
+    /// Count and preview matches without changing anything
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TitleRewrite {
+    pub table: TitledTable,
+    pub id: i64,
+    pub before: String,
+    /// `None` when the row is deleted
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryRedactionReport {
+    pub dry_run: bool,
+    pub activities_changed: u64,
+    pub time_logs_changed: u64,
+    pub screenshots_changed: u64,
+    pub screenshots_deleted: u64,
+    pub files_deleted: u64,
+    /// The first matches found, for review before a real run
+    pub preview: Vec<TitleRewrite>,
+}
+
+impl HistoryRedactionReport {
+    pub fn rows_changed(&self) -> u64 {
+        self.activities_changed + self.time_logs_changed + self.screenshots_changed + self.screenshots_deleted
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrityStatus {
+    Ok,
+    /// The file is gone
+    Missing,
+    /// The file is shorter than the size recorded at capture
+    Truncated,
+    /// The file's contents no longer match the recorded checksum
+    Altered,
+    /// The file is there but couldn't be read
+    Unreadable,
+}
+
+impl IntegrityStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntegrityStatus::Ok => "ok",
+            IntegrityStatus::Missing => "missing",
+            IntegrityStatus::Truncated => "truncated",
+            IntegrityStatus::Altered => "altered",
+            IntegrityStatus::Unreadable => "unreadable",
+        }
+    }
+
+    pub fn is_corrupt(&self) -> bool {
+        *self != IntegrityStatus::Ok
+    }
+}
+
+/// What a capture did about a sensitive focused window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivacyAction {
+    /// No image was taken; the row has no file
+    Skipped,
+    /// The window's rectangle was blacked out before encoding
+    Redacted,
+}
+
+impl PrivacyAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrivacyAction::Skipped => "skipped",
+            PrivacyAction::Redacted => "redacted",
+        }
+    }
+}
+
+impl std::str::FromStr for PrivacyAction {
+    type Err = crate::error::AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skipped" => Ok(PrivacyAction::Skipped),
+            "redacted" => Ok(PrivacyAction::Redacted),
+            _ => Err(crate::error::AppError::InvalidInput(format!("Unknown privacy action: {}", s))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityFailure {
+    pub screenshot_id: i64,
+    pub path: String,
+    pub status: IntegrityStatus,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub checked: u64,
+    pub ok: u64,
+    pub missing: u64,
+    pub truncated: u64,
+    pub altered: u64,
+    pub unreadable: u64,
+    /// Rows captured before checksums existed that had one computed by this run
+    pub backfilled: u64,
+    pub failures: Vec<IntegrityFailure>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardData {
     pub app_stats: Vec<AppStats>,
@@ -164,4 +771,53 @@ pub struct SystemStats {
     pub disk_usage: f64,
     pub uptime: u64,
     pub process_count: u32,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub level: AuditLevel,
+    pub kind: AuditKind,
+    pub message: String,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEventPage {
+    pub events: Vec<AuditEvent>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_enums_serialize_as_their_filter_values() {
+        for level in [AuditLevel::Info, AuditLevel::Warning, AuditLevel::Error, AuditLevel::Critical] {
+            assert_eq!(serde_json::to_value(level).unwrap(), level.as_str());
+            assert_eq!(serde_json::from_value::<AuditLevel>(level.as_str().into()).unwrap(), level);
+        }
+
+        let kinds = [
+            AuditKind::Start,
+            AuditKind::Stop,
+            AuditKind::Pause,
+            AuditKind::Resume,
+            AuditKind::ConfigChange,
+            AuditKind::Crash,
+            AuditKind::WatchdogRestart,
+            AuditKind::Recovery,
+            AuditKind::Integrity,
+            AuditKind::Privacy,
+            AuditKind::Redaction,
+        ];
+        for kind in kinds {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+            assert_eq!(serde_json::from_value::<AuditKind>(kind.as_str().into()).unwrap(), kind);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::Utc;
+use tauri::http::{header, Request, Response, StatusCode, Uri};
+use tauri::{AppHandle, Manager};
+
+use crate::data_layout::{unique_path, DataLayout};
+use crate::error::{AppError, Result};
+use crate::models::{IntegrityStatus, Screenshot, ScreenshotFormat};
+use crate::screenshot_encoder::encode_thumbnail;
+use crate::state::AppState;
+
+/// Custom URI scheme the webview loads screenshots from, so image bytes never
+/// travel over IPC: `soham://localhost/screenshot/{id}?size=thumb|full`.
+pub const SCHEME: &str = "soham";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSize {
+    Thumb,
+    Full,
+}
+
+impl ImageSize {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageSize::Thumb => "thumb",
+            ImageSize::Full => "full",
+        }
+    }
+}
+
+pub fn screenshot_url(id: i64, size: ImageSize) -> String {
+    // Windows and Android webviews only route custom schemes through http://<scheme>.localhost
+    #[cfg(any(windows, target_os = "android"))]
+    let base = format!("http://{}.localhost", SCHEME);
+    #[cfg(not(any(windows, target_os = "android")))]
+    let base = format!("{}://localhost", SCHEME);
+
+    format!("{}/screenshot/{}?size={}", base, id, size.as_str())
+}
+
+/// Fills in `url` and `thumbnail_url` for the webview.
+pub fn with_urls(mut screenshot: Screenshot) -> Screenshot {
+    if !screenshot.has_image() {
+        return screenshot;
+    }
+    screenshot.url = Some(screenshot_url(screenshot.id, ImageSize::Full));
+    screenshot.thumbnail_url = Some(screenshot_url(screenshot.id, ImageSize::Thumb));
+    screenshot
+}
+
+pub async fn handle(app_handle: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return text_response(StatusCode::SERVICE_UNAVAILABLE, "Application is still starting");
+    };
+
+    let Some((id, size)) = parse_screenshot_uri(request.uri()) else {
+        return text_response(StatusCode::BAD_REQUEST, "Expected /screenshot/{id}?size=thumb|full");
+    };
+
+    match serve_screenshot(state.inner(), id, size).await {
+        Ok(Some((bytes, content_type))) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CACHE_CONTROL, "private, max-age=86400")
+            .body(bytes)
+            .unwrap_or_default(),
+        Ok(None) => text_response(StatusCode::NOT_FOUND, "Screenshot not found"),
+        Err(e) => {
+            log::error!("Failed to serve screenshot {}: {}", id, e);
+            text_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+        }
+    }
+}
+
+/// Accepts both `soham://screenshot/{id}` and `soham://localhost/screenshot/{id}`.
+fn parse_screenshot_uri(uri: &Uri) -> Option<(i64, ImageSize)> {
+    let mut segments: Vec<&str> = uri.path().split('/').filter(|s| !s.is_empty()).collect();
+    if uri.host() != Some("screenshot") {
+        if segments.first() != Some(&"screenshot") {
+            return None;
+        }
+        segments.remove(0);
+    }
+
+    let [id] = segments.as_slice() else {
+        return None;
+    };
+    let id = id.parse().ok()?;
+
+    let size = match uri.query().and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("size="))) {
+        Some("thumb") => ImageSize::Thumb,
+        Some("full") | None => ImageSize::Full,
+        Some(_) => return None,
+    };
+
+    Some((id, size))
+}
+
+async fn serve_screenshot(state: &AppState, id: i64, size: ImageSize) -> Result<Option<(Vec<u8>, &'static str)>> {
+    let Some(screenshot) = state.repository.get_screenshot(id).await?.filter(Screenshot::has_image) else {
+        return Ok(None);
+    };
+
+    if size == ImageSize::Thumb {
+        if let Some(thumbnail_path) = &screenshot.thumbnail_path {
+            if let Ok(bytes) = tokio::fs::read(thumbnail_path).await {
+                return Ok(Some((bytes, ScreenshotFormat::Jpeg.mime_type())));
+            }
+        }
+    }
+
+    let bytes = match tokio::fs::read(&screenshot.path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            state
+                .repository
+                .set_screenshot_integrity(id, IntegrityStatus::Missing, None, Utc::now())
+                .await?;
+            state.cache.invalidate_screenshot_cache().await;
+            return Ok(None);
+        }
+        Err(e) => return Err(AppError::FileIO(format!("Failed to read {}: {}", screenshot.path, e))),
+    };
+
+    match size {
+        ImageSize::Full => {
+            let format = screenshot
+                .format
+                .as_deref()
+                .and_then(|f| f.parse::<ScreenshotFormat>().ok())
+                .unwrap_or(ScreenshotFormat::Png);
+            Ok(Some((bytes, format.mime_type())))
+        }
+        ImageSize::Thumb => {
+            let thumbnail = backfill_thumbnail(state, &screenshot, bytes).await?;
+            Ok(Some((thumbnail, ScreenshotFormat::Jpeg.mime_type())))
+        }
+    }
+}
+
+/// Generates and stores the thumbnail for a capture taken before thumbnails existed.
+async fn backfill_thumbnail(state: &AppState, screenshot: &Screenshot, original: Vec<u8>) -> Result<Vec<u8>> {
+    let original_path = std::path::PathBuf::from(&screenshot.path);
+    let (thumbnail, thumbnail_path) = tokio::task::spawn_blocking(move || {
+        let image = image::load_from_memory(&original)
+            .map_err(|e| AppError::ImageProcessing(format!("Failed to decode screenshot: {}", e)))?;
+        let thumbnail = encode_thumbnail(&image)?;
+
+        let thumbnail_path = unique_path(DataLayout::thumbnail_path(&original_path));
+        if let Err(e) = std::fs::write(&thumbnail_path, &thumbnail) {
+            log::warn!("Failed to save thumbnail {}: {}", thumbnail_path.display(), e);
+            return Ok::<_, AppError>((thumbnail, None));
+        }
+        Ok((thumbnail, Some(thumbnail_path)))
+    })
+    .await
+    .map_err(|e| AppError::ImageProcessing(format!("Thumbnail task failed: {}", e)))??;
+
+    if let Some(thumbnail_path) = thumbnail_path {
+        state
+            .repository
+            .set_screenshot_thumbnail(screenshot.id, &thumbnail_path.to_string_lossy())
+            .await?;
+    }
+
+    Ok(thumbnail)
+}
+
+fn text_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.as_bytes().to_vec())
+        .unwrap_or_default()
+}
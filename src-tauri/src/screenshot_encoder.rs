@@ -0,0 +1,200 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::EncodingConfig;
+use crate::error::{AppError, Result};
+use crate::models::ScreenshotFormat;
+
+/// Per-image budget for scaling and encoding (PRD compression target). Going over
+/// it shrinks the following captures until they fit again (see `EncodeBudget`).
+pub const ENCODE_BUDGET: Duration = Duration::from_millis(150);
+/// Smallest share of the configured size the budget shrinks captures to
+const MIN_BUDGET_SCALE: f32 = 0.25;
+/// Factor the budget scale changes by per capture
+const BUDGET_STEP: f32 = 0.75;
+
+/// JPEG quality when `EncodingConfig.quality` is unset.
+const JPEG_QUALITY: u8 = 80;
+
+/// Longest edge of the gallery thumbnails.
+pub const THUMBNAIL_MAX_DIMENSION: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 70;
+
+pub struct EncodedImage {
+    pub bytes: Vec<u8>,
+    pub format: ScreenshotFormat,
+    pub width: u32,
+    pub height: u32,
+    /// JPEG preview no larger than `THUMBNAIL_MAX_DIMENSION`
+    pub thumbnail: Vec<u8>,
+    pub elapsed: Duration,
+}
+
+/// Extra downscale carried from one capture to the next, so encoding stays within
+/// `ENCODE_BUDGET` on slow machines and very large displays.
+#[derive(Debug)]
+pub struct EncodeBudget {
+    scale: Mutex<f32>,
+}
+
+impl EncodeBudget {
+    pub fn new() -> Self {
+        Self { scale: Mutex::new(1.0) }
+    }
+
+    pub fn scale(&self) -> f32 {
+        *self.scale.lock().unwrap()
+    }
+
+    /// Shrinks the next capture after one that went over budget and grows it back
+    /// once encoding is well under. Returns the new scale when it changed.
+    fn record(&self, elapsed: Duration) -> Option<f32> {
+        let mut scale = self.scale.lock().unwrap();
+        let previous = *scale;
+        if elapsed > ENCODE_BUDGET {
+            *scale = (previous * BUDGET_STEP).max(MIN_BUDGET_SCALE);
+        } else if elapsed < ENCODE_BUDGET / 2 {
+            *scale = (previous / BUDGET_STEP).min(1.0);
+        }
+        (*scale != previous).then_some(*scale)
+    }
+}
+
+impl Default for EncodeBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Turns a raw capture into the bytes written to disk, following `Config.encoding`
+/// scaled down further by the shared `EncodeBudget`. Blocking: call it from
+/// `spawn_blocking`.
+pub struct ScreenshotEncoder {
+    settings: EncodingConfig,
+    budget: Arc<EncodeBudget>,
+}
+
+impl ScreenshotEncoder {
+    pub fn new(settings: EncodingConfig, budget: Arc<EncodeBudget>) -> Self {
+        Self { settings, budget }
+    }
+
+    pub fn format(&self) -> ScreenshotFormat {
+        self.settings.format
+    }
+
+    pub fn encode(&self, image: RgbaImage) -> Result<EncodedImage> {
+        let started = Instant::now();
+
+        let mut image = self.resize(DynamicImage::ImageRgba8(image));
+        if self.settings.grayscale {
+            image = DynamicImage::ImageLuma8(image.to_luma8());
+        }
+        let (width, height) = image.dimensions();
+        let thumbnail = encode_thumbnail(&image)?;
+
+        let mut bytes = Vec::new();
+        let result = match self.settings.format {
+            ScreenshotFormat::Png => image.write_with_encoder(PngEncoder::new_with_quality(
+                &mut bytes,
+                CompressionType::Fast,
+                PngFilter::Adaptive,
+            )),
+            ScreenshotFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let image = if self.settings.grayscale { image } else { DynamicImage::ImageRgb8(image.to_rgb8()) };
+                image.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, self.settings.quality.unwrap_or(JPEG_QUALITY)))
+            }
+            ScreenshotFormat::Webp => image.write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+        };
+        result.map_err(|e| {
+            AppError::ImageProcessing(format!("Failed to encode {}: {}", self.settings.format.as_str(), e))
+        })?;
+
+        let elapsed = started.elapsed();
+        match self.budget.record(elapsed) {
+            Some(scale) if elapsed > ENCODE_BUDGET => log::warn!(
+                "Encoding a {}x{} {} took {}ms (budget {}ms); shrinking the next captures to {:.0}% of the configured size",
+                width,
+                height,
+                self.settings.format.as_str(),
+                elapsed.as_millis(),
+                ENCODE_BUDGET.as_millis(),
+                scale * 100.0
+            ),
+            Some(scale) => log::info!("Encoding is back under budget; captures now at {:.0}% of the configured size", scale * 100.0),
+            None => {}
+        }
+
+        Ok(EncodedImage {
+            bytes,
+            format: self.settings.format,
+            width,
+            height,
+            thumbnail,
+            elapsed,
+        })
+    }
+
+    fn resize(&self, image: DynamicImage) -> DynamicImage {
+        let (width, height) = image.dimensions();
+        let mut scale = self.settings.scale.clamp(0.01, 1.0) as f64;
+        if let Some(max_dimension) = self.settings.max_dimension {
+            let longest = width.max(height) as f64 * scale;
+            if longest > max_dimension as f64 {
+                scale *= max_dimension as f64 / longest;
+            }
+        }
+        scale *= self.budget.scale() as f64;
+
+        if scale >= 1.0 {
+            return image;
+        }
+
+        let new_width = ((width as f64 * scale).round() as u32).max(1);
+        let new_height = ((height as f64 * scale).round() as u32).max(1);
+        // Triangle is several times faster than Lanczos3 and indistinguishable for screen content
+        image.resize_exact(new_width, new_height, FilterType::Triangle)
+    }
+}
+
+/// Thumbnail for an already decoded image; also used to backfill captures taken before thumbnails existed.
+pub fn encode_thumbnail(image: &DynamicImage) -> Result<Vec<u8>> {
+    let thumbnail = image.thumbnail(THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION);
+    let thumbnail = match thumbnail {
+        DynamicImage::ImageLuma8(_) => thumbnail,
+        other => DynamicImage::ImageRgb8(other.to_rgb8()),
+    };
+
+    let mut bytes = Vec::new();
+    thumbnail
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_QUALITY))
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode thumbnail: {}", e)))?;
+    Ok(bytes)
+}
+
+/// 64-bit difference hash (dHash): each bit says whether a pixel of the 9x8
+/// grayscale thumbnail is brighter than its right neighbour.
+pub fn perceptual_hash(image: &RgbaImage) -> u64 {
+    let small = DynamicImage::ImageRgba8(imageops::thumbnail(image, 9, 8)).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot, OnceCell};
+use tokio::time::{timeout, timeout_at, Duration, Instant};
+
+use crate::cache::CacheManager;
+use crate::database::Repository;
+use crate::error::{AppError, Result};
+use crate::models::{ActivityWrite, AppIdentity, AppTransition, TimeLog, WindowActivity, WriterMetrics};
+
+/// Writes waiting beyond this make producers wait (backpressure)
+const QUEUE_CAPACITY: usize = 1024;
+/// Largest number of writes applied in one transaction
+const BATCH_SIZE: usize = 128;
+/// How long a batch stays open for more writes after its first one
+const FLUSH_INTERVAL: Duration = Duration::from_millis(10);
+/// Failed writes are retried ahead of later batches, at most this many times
+const MAX_ATTEMPTS: u32 = 5;
+/// How soon failed writes are retried when nothing else is queued
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+enum Queued {
+    Write(ActivityWrite, Instant),
+    /// Answered once everything queued before it is written, with the number of
+    /// writes still waiting for a retry
+    Flush(oneshot::Sender<usize>),
+}
+
+#[derive(Default)]
+struct Counters {
+    max_queue_depth: AtomicUsize,
+    written: AtomicU64,
+    failed: AtomicU64,
+    batches: AtomicU64,
+    last_batch_size: AtomicUsize,
+    last_write_latency_ms: AtomicU64,
+}
+
+/// Queues focus events, time logs and transitions for a single writer task that
+/// applies them in batched transactions, so trackers never wait on SQLite unless
+/// the queue is full. Activity ids are handed out up front: every
+/// `window_activities` insert has to go through here.
+///
+/// After each committed batch the writer emits `window-activity` for the new rows
+/// and invalidates the dashboard cache once.
+#[derive(Clone)]
+pub struct ActivityWriter {
+    repository: Arc<Repository>,
+    sender: mpsc::Sender<Queued>,
+    /// Taken by `spawn`
+    receiver: Arc<Mutex<Option<mpsc::Receiver<Queued>>>>,
+    next_activity_id: Arc<OnceCell<AtomicI64>>,
+    /// `apps.id` by stable id
+    apps: Arc<Mutex<HashMap<String, i64>>>,
+    /// Writes that failed, in queue order, with how often they've been tried
+    retries: Arc<Mutex<Vec<(ActivityWrite, u32)>>>,
+    counters: Arc<Counters>,
+}
+
+impl ActivityWriter {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+
+        Self {
+            repository: Arc::new(Repository::new(pool)),
+            sender,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+            next_activity_id: Arc::new(OnceCell::new()),
+            apps: Arc::new(Mutex::new(HashMap::new())),
+            retries: Arc::new(Mutex::new(Vec::new())),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Starts the writer task. Only the first call does anything.
+    pub fn spawn(&self, app_handle: AppHandle, cache: Arc<CacheManager>) {
+        let Some(mut receiver) = self.receiver.lock().unwrap().take() else {
+            return;
+        };
+        let writer = self.clone();
+
+        tokio::spawn(async move {
+            // Warm up so that only apps never seen before wait on the database
+            if let Err(e) = writer.next_activity_id().await {
+                log::warn!("Failed to read the last activity id: {}", e);
+            }
+            match writer.repository.get_apps().await {
+                Ok(apps) => writer
+                    .apps
+                    .lock()
+                    .unwrap()
+                    .extend(apps.into_iter().map(|app| (app.stable_id, app.id))),
+                Err(e) => log::warn!("Failed to load the apps registry: {}", e),
+            }
+
+            loop {
+                // With failed writes waiting, don't sleep on the queue forever
+                let first = if writer.pending_retries() > 0 {
+                    match timeout(RETRY_INTERVAL, receiver.recv()).await {
+                        Ok(Some(queued)) => Some(queued),
+                        Ok(None) => break,
+                        Err(_) => None,
+                    }
+                } else {
+                    match receiver.recv().await {
+                        Some(queued) => Some(queued),
+                        None => break,
+                    }
+                };
+
+                let mut writes = Vec::new();
+                let mut flushed = Vec::new();
+                let deadline = Instant::now() + FLUSH_INTERVAL;
+
+                let mut next = first;
+                while let Some(queued) = next.take() {
+                    match queued {
+                        Queued::Write(write, queued_at) => writes.push((write, queued_at)),
+                        Queued::Flush(done) => {
+                            flushed.push(done);
+                            break;
+                        }
+                    }
+                    if writes.len() >= BATCH_SIZE {
+                        break;
+                    }
+                    next = timeout_at(deadline, receiver.recv()).await.ok().flatten();
+                }
+
+                writer.write(&app_handle, &cache, writes).await;
+                let pending = writer.pending_retries();
+                for done in flushed {
+                    let _ = done.send(pending);
+                }
+            }
+        });
+    }
+
+    /// The `apps.id` for `app`. Only an app's first sighting waits on the database;
+    /// later ones queue the registry refresh.
+    pub async fn app_ref(&self, app: &AppIdentity, seen: DateTime<Utc>) -> Result<i64> {
+        let stable_id = app.stable_id();
+        let known = self.apps.lock().unwrap().get(&stable_id).copied();
+
+        match known {
+            Some(id) => {
+                self.push(ActivityWrite::App { app: app.clone(), seen }).await?;
+                Ok(id)
+            }
+            None => {
+                // Registering a real identity can merge away the app's `name:` entry;
+                // rows still queued with that entry's id have to land first
+                let name_id = format!("name:{}", app.name);
+                let merges = stable_id != name_id && self.apps.lock().unwrap().contains_key(&name_id);
+                if merges {
+                    if let Err(e) = self.flush().await {
+                        log::warn!("Failed to flush before registering {}: {}", stable_id, e);
+                    }
+                }
+
+                let id = self.repository.upsert_app(app, seen).await?;
+                let mut apps = self.apps.lock().unwrap();
+                if stable_id != name_id {
+                    apps.remove(&name_id);
+                }
+                apps.insert(stable_id, id);
+                Ok(id)
+            }
+        }
+    }
+
+    /// Queues `activity` and returns it with the id its row will have.
+    pub async fn activity(&self, mut activity: WindowActivity) -> Result<WindowActivity> {
+        activity.id = Some(self.next_activity_id().await?.fetch_add(1, Ordering::SeqCst));
+
+        self.push(ActivityWrite::Activity(activity.clone())).await?;
+        Ok(activity)
+    }
+
+    pub async fn time_log(&self, time_log: TimeLog) -> Result<()> {
+        self.push(ActivityWrite::TimeLog(time_log)).await
+    }
+
+    pub async fn transition(&self, transition: AppTransition) -> Result<()> {
+        self.push(ActivityWrite::Transition(transition)).await
+    }
+
+    /// Waits until everything queued so far is written. Before `spawn` the queue is
+    /// written from here instead. Fails if some writes are still waiting for a retry.
+    pub async fn flush(&self) -> Result<()> {
+        let unstarted = self.receiver.lock().unwrap().take();
+        let pending = match unstarted {
+            Some(mut receiver) => {
+                let mut writes = Vec::new();
+                while let Ok(queued) = receiver.try_recv() {
+                    if let Queued::Write(write, queued_at) = queued {
+                        writes.push((write, queued_at));
+                    }
+                }
+                *self.receiver.lock().unwrap() = Some(receiver);
+                self.apply(writes).await;
+                self.pending_retries()
+            }
+            None => {
+                let (done, written) = oneshot::channel();
+                self.sender
+                    .send(Queued::Flush(done))
+                    .await
+                    .map_err(|_| AppError::Writer("Activity writer stopped".to_string()))?;
+                written
+                    .await
+                    .map_err(|_| AppError::Writer("Activity writer stopped before flushing".to_string()))?
+            }
+        };
+
+        match pending {
+            0 => Ok(()),
+            pending => Err(AppError::Writer(format!("{} activity writes are still failing", pending))),
+        }
+    }
+
+    pub fn metrics(&self) -> WriterMetrics {
+        WriterMetrics {
+            queue_depth: self.queue_depth(),
+            queue_capacity: QUEUE_CAPACITY,
+            max_queue_depth: self.counters.max_queue_depth.load(Ordering::Relaxed),
+            written: self.counters.written.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            pending_retries: self.pending_retries(),
+            batches: self.counters.batches.load(Ordering::Relaxed),
+            last_batch_size: self.counters.last_batch_size.load(Ordering::Relaxed),
+            last_write_latency_ms: self.counters.last_write_latency_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn next_activity_id(&self) -> Result<&AtomicI64> {
+        self.next_activity_id
+            .get_or_try_init(|| async { self.repository.last_window_activity_id().await.map(|id| AtomicI64::new(id + 1)) })
+            .await
+    }
+
+    fn pending_retries(&self) -> usize {
+        self.retries.lock().unwrap().len()
+    }
+
+    fn queue_depth(&self) -> usize {
+        QUEUE_CAPACITY - self.sender.capacity()
+    }
+
+    async fn push(&self, write: ActivityWrite) -> Result<()> {
+        self.sender
+            .send(Queued::Write(write, Instant::now()))
+            .await
+            .map_err(|_| AppError::Writer("Activity writer stopped".to_string()))?;
+        self.counters.max_queue_depth.fetch_max(self.queue_depth(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Applies one batch and announces what it wrote.
+    async fn write(&self, app_handle: &AppHandle, cache: &CacheManager, batch: Vec<(ActivityWrite, Instant)>) {
+        let written = self.apply(batch).await;
+
+        let mut changed_activity = false;
+        for write in &written {
+            match write {
+                ActivityWrite::Activity(activity) => {
+                    changed_activity = true;
+                    if let Err(e) = app_handle.emit("window-activity", activity) {
+                        log::error!("Failed to emit window activity event: {}", e);
+                    }
+                }
+                ActivityWrite::TimeLog(_) => changed_activity = true,
+                ActivityWrite::App { .. } | ActivityWrite::Transition(_) => {}
+            }
+        }
+        if changed_activity {
+            cache.invalidate_dashboard_cache().await;
+        }
+    }
+
+    /// Writes earlier failures followed by `batch` in one transaction. If that
+    /// fails, each write is retried on its own so one bad row doesn't take the rest
+    /// with it; writes that still fail are kept for the next batch, since later
+    /// rows may already refer to their ids. Returns what was written.
+    async fn apply(&self, batch: Vec<(ActivityWrite, Instant)>) -> Vec<ActivityWrite> {
+        let retries = std::mem::take(&mut *self.retries.lock().unwrap());
+        if batch.is_empty() && retries.is_empty() {
+            return Vec::new();
+        }
+
+        let oldest = batch.iter().map(|(_, queued_at)| *queued_at).min().unwrap_or_else(Instant::now);
+        let writes: Vec<(ActivityWrite, u32)> = retries
+            .into_iter()
+            .chain(batch.into_iter().map(|(write, _)| (write, 0)))
+            .collect();
+        let rows: Vec<ActivityWrite> = writes.iter().map(|(write, _)| write.clone()).collect();
+
+        let written = match self.repository.write_activities(&rows).await {
+            Ok(()) => rows,
+            Err(e) => {
+                log::warn!("Failed to write batch of {} activity rows, retrying one by one: {}", rows.len(), e);
+                let mut written = Vec::with_capacity(writes.len());
+                let mut failed = Vec::new();
+                for (write, attempts) in writes {
+                    match self.repository.write_activities(std::slice::from_ref(&write)).await {
+                        Ok(()) => written.push(write),
+                        Err(e) if attempts + 1 < MAX_ATTEMPTS => {
+                            log::warn!("Failed to write activity row {:?}, will retry: {}", write, e);
+                            failed.push((write, attempts + 1));
+                        }
+                        Err(e) => {
+                            log::error!("Dropping activity row {:?} after {} attempts: {}", write, MAX_ATTEMPTS, e);
+                            self.counters.failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+                // Anything queued meanwhile goes after these
+                self.retries.lock().unwrap().splice(0..0, failed);
+                written
+            }
+        };
+
+        self.counters.batches.fetch_add(1, Ordering::Relaxed);
+        self.counters.written.fetch_add(written.len() as u64, Ordering::Relaxed);
+        self.counters.last_batch_size.store(written.len(), Ordering::Relaxed);
+        self.counters
+            .last_write_latency_ms
+            .store(oldest.elapsed().as_millis() as u64, Ordering::Relaxed);
+
+        written
+    }
+}
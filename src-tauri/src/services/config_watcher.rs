@@ -0,0 +1,133 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+
+use crate::config::{Config, ConfigChange};
+use crate::error::{AppError, Result};
+use crate::models::{AuditKind, AuditLevel};
+use crate::state::AppState;
+
+/// Payload of the `config-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChangedPayload {
+    pub changes: Vec<ConfigChange>,
+    pub config: Config,
+}
+
+/// Watches `config.toml` and pushes validated changes into `AppState`.
+pub struct ConfigWatcher;
+
+impl ConfigWatcher {
+    pub fn spawn(app_handle: AppHandle, state: AppState) {
+        tokio::spawn(async move {
+            if let Err(e) = Self::watch(app_handle, state).await {
+                log::error!("Config watcher failed: {}", e);
+            }
+        });
+    }
+
+    async fn watch(app_handle: AppHandle, state: AppState) -> Result<()> {
+        let config_path = Config::path();
+        let config_dir = config_path
+            .parent()
+            .map(PathBuf::from)
+            .ok_or_else(|| AppError::Config("Config path has no parent directory".to_string()))?;
+        tokio::fs::create_dir_all(&config_dir).await?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let watched_path = config_path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) => {
+                    let relevant = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) && event.paths.iter().any(|p| p == &watched_path);
+                    if relevant {
+                        let _ = tx.send(());
+                    }
+                }
+                Err(e) => log::warn!("Config watch error: {}", e),
+            }
+        })
+        .map_err(|e| AppError::Config(format!("Failed to create config watcher: {}", e)))?;
+
+        // Watch the directory rather than the file: editors usually save by
+        // writing a temp file and renaming it over the original.
+        watcher
+            .watch(&config_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| AppError::Config(format!("Failed to watch {}: {}", config_dir.display(), e)))?;
+
+        log::info!("Watching {} for changes", config_path.display());
+
+        while rx.recv().await.is_some() {
+            // Let a burst of write/rename events settle before reading the file
+            sleep(Duration::from_millis(500)).await;
+            while rx.try_recv().is_ok() {}
+
+            Self::reload(&app_handle, &state, &config_path).await;
+        }
+
+        Ok(())
+    }
+
+    async fn reload(app_handle: &AppHandle, state: &AppState, config_path: &Path) {
+        let new_config = match Config::read_from(config_path) {
+            Ok(Some(config)) => config,
+            Ok(None) => {
+                log::warn!("{} was removed, keeping the current configuration", config_path.display());
+                return;
+            }
+            Err(e) => {
+                log::error!("Rejected invalid config {}: {}", config_path.display(), e);
+                if let Err(audit_err) = state
+                    .audit
+                    .record(
+                        AuditLevel::Warning,
+                        AuditKind::ConfigChange,
+                        &format!("Rejected invalid configuration: {}", e),
+                        None,
+                    )
+                    .await
+                {
+                    log::error!("Failed to audit rejected config: {}", audit_err);
+                }
+                return;
+            }
+        };
+
+        let changes = state.config().diff(&new_config);
+        if changes.is_empty() {
+            return;
+        }
+
+        state.replace_config(new_config.clone());
+
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        log::info!("Configuration reloaded, changed: {}", fields.join(", "));
+
+        if let Err(e) = state
+            .audit
+            .record(
+                AuditLevel::Info,
+                AuditKind::ConfigChange,
+                &format!("Configuration reloaded ({})", fields.join(", ")),
+                serde_json::to_value(&changes).ok(),
+            )
+            .await
+        {
+            log::error!("Failed to audit config change: {}", e);
+        }
+
+        let payload = ConfigChangedPayload {
+            changes,
+            config: new_config,
+        };
+        if let Err(e) = app_handle.emit("config-changed", &payload) {
+            log::error!("Failed to emit config-changed event: {}", e);
+        }
+    }
+}
@@ -1,162 +1,277 @@
-use chrono::Utc;
-use tauri::{AppHandle, Emitter};
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration};
 
-use crate::database::Repository;
 use crate::error::Result;
-use crate::models::{EventType, WindowActivity};
+use crate::models::{AppIdentity, AppTransition, EventType, WindowActivity, WindowMetadata};
+use crate::privacy::SensitiveWindowMatcher;
+use crate::services::focus_tracker::CurrentFocus;
+use crate::services::window_source::{self, FocusedWindow, PollingWindowSource, WindowSource};
 use crate::state::AppState;
 
+/// Turns the focus changes reported by a `WindowSource` into `window_activities`
+/// rows, focus intervals and `app_transitions`.
 pub struct EventMonitor;
 
 impl EventMonitor {
-    #[cfg(target_os = "macos")]
-    pub fn spawn(repository: Repository, app_handle: AppHandle, state: AppState) {
+    /// Follows `source`; if it stops reporting, focus tracking falls back to polling.
+    pub fn spawn(state: AppState, source: Box<dyn WindowSource>) {
         tokio::spawn(async move {
-            if let Err(e) = Self::start_macos_event_monitoring(repository, app_handle, state).await {
-                log::error!("macOS event monitoring failed: {}", e);
-            }
-        });
-    }
+            let mut source = source;
 
-    #[cfg(not(target_os = "macos"))]
-    pub fn spawn(repository: Repository, app_handle: AppHandle, state: AppState) {
-        tokio::spawn(async move {
-            if let Err(e) = Self::start_polling_event_monitoring(repository, app_handle, state).await {
-                log::error!("Polling event monitoring failed: {}", e);
+            loop {
+                let name = source.name();
+                log::info!("Focus tracking using {} source", name);
+                Self::follow(&state, source.start()).await;
+
+                if name == "polling" {
+                    log::error!("Polling window source stopped, focus tracking disabled");
+                    return;
+                }
+                log::warn!("{} window source stopped, falling back to polling", name);
+                source = Box::new(PollingWindowSource::default());
             }
         });
     }
 
-    #[cfg(target_os = "macos")]
-    async fn start_macos_event_monitoring(
-        repository: Repository,
-        app_handle: AppHandle,
-        state: AppState,
-    ) -> Result<()> {
-        use active_win_pos_rs::get_active_window;
-        use tokio::time::{interval, Duration};
-
-        let mut ticker = interval(Duration::from_millis(500));
-        let mut last_window: Option<(String, String)> = None;
+    /// Records focus changes until the source's channel closes.
+    async fn follow(state: &AppState, mut windows: mpsc::UnboundedReceiver<FocusedWindow>) {
+        // Pause and idle changes don't come from the source, so they are re-checked on a tick
+        let mut ticker = interval(Duration::from_secs(1));
+        let mut focused: Option<FocusedWindow> = None;
+        // With when focus entered its app, which may have been in an earlier window
+        let mut recorded: Option<(FocusedWindow, DateTime<Utc>)> = None;
 
         loop {
-            ticker.tick().await;
+            tokio::select! {
+                window = windows.recv() => match window {
+                    Some(window) => focused = Some(window),
+                    None => {
+                        if recorded.is_some() {
+                            Self::close_focus(state).await;
+                        }
+                        return;
+                    }
+                },
+                _ = ticker.tick() => {}
+            }
 
-            if state.is_paused().await {
+            if state.is_paused().await || state.is_idle().await {
+                if recorded.take().is_some() {
+                    Self::close_focus(state).await;
+                }
                 continue;
             }
 
-            if let Ok(window) = get_active_window() {
-                let current_window = (window.app_name.clone(), window.title.clone());
-                
-                if last_window.as_ref() != Some(&current_window) {
-                    if let Some((last_app, last_title)) = last_window {
-                        let _ = Self::record_event(
-                            &repository,
-                            &app_handle,
-                            &state,
-                            &last_app,
-                            &last_title,
-                            EventType::Blur,
-                        ).await;
-                    }
-
-                    let _ = Self::record_event(
-                        &repository,
-                        &app_handle,
-                        &state,
-                        &window.app_name,
-                        &window.title,
-                        EventType::Focus,
-                    ).await;
-
-                    last_window = Some(current_window);
-                }
+            let Some(window) = &focused else {
+                continue;
+            };
+            if recorded.as_ref().is_some_and(|(recorded, _)| recorded.same_focus(window)) {
+                continue;
             }
 
-            tokio::time::sleep(Duration::from_millis(100)).await;
+            let entered_app = Self::switch_focus(state, recorded.take(), window).await;
+            recorded = Some((window.clone(), entered_app));
         }
     }
 
-    #[cfg(not(target_os = "macos"))]
-    async fn start_polling_event_monitoring(
-        repository: Repository,
-        app_handle: AppHandle,
-        state: AppState,
-    ) -> Result<()> {
-        use active_win_pos_rs::get_active_window;
-        use tokio::time::{interval, Duration};
-
-        let mut ticker = interval(Duration::from_secs(2));
-        let mut last_window: Option<(String, String)> = None;
+    /// Records the switch and returns when focus entered `current`'s app.
+    async fn switch_focus(
+        state: &AppState,
+        previous: Option<(FocusedWindow, DateTime<Utc>)>,
+        current: &FocusedWindow,
+    ) -> DateTime<Utc> {
+        let blurred = match &previous {
+            Some((previous, _)) => Self::record_event(state, &previous.app, &previous.title, EventType::Blur, None).await.ok(),
+            None => None,
+        };
+        let previous_since = state.focus.current().await.map(|focus| focus.since);
+        let same_app = previous
+            .as_ref()
+            .is_some_and(|(previous, _)| previous.app.stable_id() == current.app.stable_id());
 
-        loop {
-            ticker.tick().await;
+        let mut metadata = current.metadata.clone();
+        window_source::locate(&mut metadata, &window_source::displays().await);
+        let metadata = serde_json::to_string(&metadata).ok();
+        let activity = match Self::record_event(state, &current.app, &current.title, EventType::Focus, metadata).await {
+            Ok(activity) => activity,
+            Err(e) => {
+                log::error!("Failed to record focus event: {}", e);
+                return Utc::now();
+            }
+        };
 
-            if state.is_paused().await {
-                continue;
+        // No transition after pause or idle: nothing was focused right before
+        if let (Some((previous, previous_entered_app)), Some(blurred)) = (&previous, &blurred) {
+            // Time in the app for app switches, in the window for switches within an app
+            let since = if same_app { previous_since } else { Some(*previous_entered_app) };
+            let transition = AppTransition {
+                id: None,
+                session_id: activity.session_id,
+                from_app_id: blurred.app_id.clone(),
+                from_app_ref: blurred.app_ref,
+                to_app_id: activity.app_id.clone(),
+                to_app_ref: activity.app_ref,
+                transition_type: current.transition_from(previous).as_str().to_string(),
+                timestamp: activity.timestamp,
+                dwell: since.map_or(0, |since| (activity.timestamp - since).num_seconds().max(0)),
+            };
+            if let Err(e) = state.writer.transition(transition).await {
+                log::error!("Failed to record app transition: {}", e);
             }
+        }
 
-            if let Ok(window) = get_active_window() {
-                let current_window = (window.app_name.clone(), window.title.clone());
-                
-                if last_window.as_ref() != Some(&current_window) {
-                    if let Some((last_app, last_title)) = last_window {
-                        let _ = Self::record_event(
-                            &repository,
-                            &app_handle,
-                            &state,
-                            &last_app,
-                            &last_title,
-                            EventType::Blur,
-                        ).await;
-                    }
+        let entered_app = match &previous {
+            Some((_, entered_app)) if same_app => *entered_app,
+            _ => activity.timestamp,
+        };
 
-                    let _ = Self::record_event(
-                        &repository,
-                        &app_handle,
-                        &state,
-                        &window.app_name,
-                        &window.title,
-                        EventType::Focus,
-                    ).await;
+        let focus = CurrentFocus {
+            activity_id: activity.id,
+            session_id: activity.session_id,
+            app_id: activity.app_id,
+            app_ref: activity.app_ref,
+            window_title: activity.window_title,
+            since: activity.timestamp,
+            sensitive: SensitiveWindowMatcher::new(&state.config().privacy)
+                .expect("privacy rules are checked by Config::validate")
+                .find(&current.app.name, &current.title),
+            bounds: match current.metadata {
+                WindowMetadata { x: Some(x), y: Some(y), width: Some(width), height: Some(height), .. }
+                    if width >= 1 && height >= 1 => Some((x, y, width, height)),
+                _ => None,
+            },
+        };
+        if let Err(e) = state.focus.begin(&state.writer, focus).await {
+            log::error!("Failed to persist focus interval: {}", e);
+        }
 
-                    last_window = Some(current_window);
-                }
-            }
+        entered_app
+    }
+
+    async fn close_focus(state: &AppState) {
+        if let Err(e) = state.focus.close(&state.writer, Utc::now()).await {
+            log::error!("Failed to persist focus interval: {}", e);
         }
     }
 
     async fn record_event(
-        repository: &Repository,
-        app_handle: &AppHandle,
         state: &AppState,
-        app_id: &str,
+        app: &AppIdentity,
         window_title: &str,
         event_type: EventType,
-    ) -> Result<()> {
+        metadata: Option<String>,
+    ) -> Result<WindowActivity> {
+        // Redact before anything is stored, emitted or handed to the focus tracker
+        let window_title = state.redactor().redact(&app.name, window_title);
+
+        let timestamp = Utc::now();
+        let app_ref = state.writer.app_ref(app, timestamp).await?;
+
         let activity = WindowActivity {
             id: None,
             session_id: state.get_current_session_id().await,
-            app_id: app_id.to_string(),
-            window_title: window_title.to_string(),
+            app_id: app.name.clone(),
+            app_ref: Some(app_ref),
+            window_title,
             event_type: event_type.as_str().to_string(),
-            timestamp: Utc::now(),
+            timestamp,
             duration: None,
-            metadata: None,
+            metadata,
         };
 
-        let activity_id = repository.insert_window_activity(&activity).await?;
+        // Emitted, and the dashboard cache invalidated, once the writer has stored it
+        state.writer.activity(activity).await
+    }
+}
 
-        let mut final_activity = activity;
-        final_activity.id = Some(activity_id);
+#[cfg(test)]
+mod tests {
+    use sqlx::Row;
 
-        if let Err(e) = app_handle.emit("window-activity", &final_activity) {
-            log::error!("Failed to emit window activity event: {}", e);
-        }
+    use super::*;
+    use crate::services::window_source::scripted::ScriptedWindowSource;
+
+    #[tokio::test]
+    async fn scripted_timeline_reaches_the_database() {
+        let state = AppState::in_memory().await;
+        let source = ScriptedWindowSource::parse(
+            r#"[
+                {"app_id": "code", "title": "main.rs", "exe_path": "/usr/bin/code"},
+                {"after_ms": 100, "app_id": "chat", "title": "general"},
+                {"after_ms": 100, "app_id": "chat", "title": "dm", "new_window": true},
+                {"after_ms": 100, "app_id": "code", "title": "main.rs", "exe_path": "/usr/bin/code"}
+            ]"#,
+        )
+        .unwrap();
+
+        EventMonitor::spawn(state.clone(), Box::new(source));
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        state.focus.close(&state.writer, Utc::now()).await.unwrap();
+        state.writer.flush().await.unwrap();
+        let pool = state.repository.pool();
+
+        let activities = sqlx::query(
+            r#"
+            SELECT window_activities.id, app_id, window_title, event_type, apps.stable_id
+            FROM window_activities
+            JOIN apps ON apps.id = window_activities.app_ref
+            ORDER BY window_activities.id
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let events: Vec<(String, String, String, String)> = activities
+            .iter()
+            .map(|row| (row.get("event_type"), row.get("app_id"), row.get("window_title"), row.get("stable_id")))
+            .collect();
+        assert_eq!(
+            events.iter().map(|(a, b, c, d)| (a.as_str(), b.as_str(), c.as_str(), d.as_str())).collect::<Vec<_>>(),
+            vec![
+                ("focus", "code", "main.rs", "exe:/usr/bin/code"),
+                ("blur", "code", "main.rs", "exe:/usr/bin/code"),
+                ("focus", "chat", "general", "name:chat"),
+                ("blur", "chat", "general", "name:chat"),
+                ("focus", "chat", "dm", "name:chat"),
+                ("blur", "chat", "dm", "name:chat"),
+                ("focus", "code", "main.rs", "exe:/usr/bin/code"),
+            ]
+        );
 
-        state.cache.invalidate_dashboard_cache().await;
+        // Every focus row has the interval that followed it
+        let focus_ids: Vec<i64> = activities
+            .iter()
+            .filter(|row| row.get::<String, _>("event_type") == "focus")
+            .map(|row| row.get("id"))
+            .collect();
+        let time_logs: Vec<(Option<i64>, String)> =
+            sqlx::query_as("SELECT activity_id, app_id FROM time_logs ORDER BY focus_start, id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            time_logs.iter().map(|(id, app)| (*id, app.as_str())).collect::<Vec<_>>(),
+            vec![
+                (Some(focus_ids[0]), "code"),
+                (Some(focus_ids[1]), "chat"),
+                (Some(focus_ids[2]), "chat"),
+                (Some(focus_ids[3]), "code"),
+            ]
+        );
 
-        Ok(())
+        let transitions: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT from_app_id, to_app_id, transition_type FROM app_transitions ORDER BY id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            transitions.iter().map(|(a, b, c)| (a.as_str(), b.as_str(), c.as_str())).collect::<Vec<_>>(),
+            vec![
+                ("code", "chat", "app_switch"),
+                ("chat", "chat", "new_window"),
+                ("chat", "code", "app_switch"),
+            ]
+        );
     }
-}
\ No newline at end of file
+}
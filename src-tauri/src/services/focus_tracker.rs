@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::error::Result;
+use crate::models::TimeLog;
+use crate::privacy::SensitiveMatch;
+use crate::services::ActivityWriter;
+
+/// The window that currently holds focus and when it gained it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrentFocus {
+    pub activity_id: Option<i64>,
+    pub session_id: i64,
+    pub app_id: String,
+    pub app_ref: Option<i64>,
+    pub window_title: String,
+    pub since: DateTime<Utc>,
+    /// Privacy rule the window tripped when it gained focus, judged on its unredacted title
+    pub sensitive: Option<SensitiveMatch>,
+    /// `(x, y, width, height)` in desktop coordinates, when the source reported it
+    pub bounds: Option<(i64, i64, i64, i64)>,
+}
+
+/// In-memory "current focus" record. Each focus change, idle timeout or
+/// shutdown closes the open interval and turns it into a `time_logs` row.
+pub struct FocusTracker {
+    current: RwLock<Option<CurrentFocus>>,
+}
+
+impl FocusTracker {
+    pub fn new() -> Self {
+        Self {
+            current: RwLock::new(None),
+        }
+    }
+
+    pub async fn current(&self) -> Option<CurrentFocus> {
+        self.current.read().await.clone()
+    }
+
+    /// Starts a new interval, queueing the one it replaces.
+    pub async fn begin(&self, writer: &ActivityWriter, focus: CurrentFocus) -> Result<()> {
+        let previous = self.current.write().await.replace(focus.clone());
+        match previous {
+            Some(previous) => Self::persist(writer, previous, focus.since).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Closes the open interval at `at` (if any) and queues it.
+    pub async fn close(&self, writer: &ActivityWriter, at: DateTime<Utc>) -> Result<()> {
+        let previous = self.current.write().await.take();
+        match previous {
+            Some(previous) => Self::persist(writer, previous, at).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn persist(writer: &ActivityWriter, focus: CurrentFocus, end: DateTime<Utc>) -> Result<()> {
+        if end <= focus.since {
+            return Ok(());
+        }
+
+        let time_log = TimeLog {
+            id: None,
+            session_id: focus.session_id,
+            activity_id: focus.activity_id,
+            app_id: focus.app_id,
+            app_ref: focus.app_ref,
+            window_title: focus.window_title,
+            focus_start: focus.since,
+            focus_end: end,
+            duration: (end - focus.since).num_seconds(),
+        };
+
+        writer.time_log(time_log).await
+    }
+}
+
+impl Default for FocusTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,339 @@
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+
+use crate::error::{AppError, Result};
+use crate::models::{EventType, WindowActivity};
+use crate::state::AppState;
+
+/// Reports how long the user has gone without keyboard or mouse input.
+pub trait IdleSource: Send + Sync {
+    /// Time since the last input, or `None` when the source cannot tell right now.
+    fn idle_time(&self) -> Option<Duration>;
+
+    fn name(&self) -> &'static str;
+}
+
+/// Idle time from the X11 MIT-SCREEN-SAVER extension.
+#[cfg(target_os = "linux")]
+pub struct X11IdleSource {
+    connection: x11rb::rust_connection::RustConnection,
+    root: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl X11IdleSource {
+    pub fn connect() -> Result<Self> {
+        use x11rb::connection::Connection;
+        use x11rb::protocol::screensaver::ConnectionExt;
+
+        let (connection, screen_num) = x11rb::connect(None).map_err(|e| {
+            AppError::SystemMonitoring(format!("Failed to connect to X11: {}", e))
+        })?;
+        let root = connection.setup().roots[screen_num].root;
+
+        // Probe once so a server without the extension is rejected up front
+        connection
+            .screensaver_query_info(root)
+            .map_err(|e| AppError::SystemMonitoring(e.to_string()))?
+            .reply()
+            .map_err(|e| {
+                AppError::SystemMonitoring(format!("XScreenSaver unavailable: {}", e))
+            })?;
+
+        Ok(Self { connection, root })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl IdleSource for X11IdleSource {
+    fn idle_time(&self) -> Option<Duration> {
+        use x11rb::protocol::screensaver::ConnectionExt;
+
+        let reply = self.connection.screensaver_query_info(self.root).ok()?.reply().ok()?;
+        Some(Duration::from_millis(reply.ms_since_user_input as u64))
+    }
+
+    fn name(&self) -> &'static str {
+        "x11-screensaver"
+    }
+}
+
+/// Idle time from systemd-logind's `IdleHint`/`IdleSinceHint` session properties.
+/// Coarser than X11 (the hint is set by the desktop), but works on Wayland too.
+#[cfg(target_os = "linux")]
+pub struct LogindIdleSource {
+    session_id: String,
+}
+
+#[cfg(target_os = "linux")]
+impl LogindIdleSource {
+    pub fn new() -> Self {
+        Self {
+            session_id: std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl IdleSource for LogindIdleSource {
+    fn idle_time(&self) -> Option<Duration> {
+        use std::process::Command;
+
+        let output = Command::new("loginctl")
+            .args(["show-session", &self.session_id, "-p", "IdleHint", "-p", "IdleSinceHint"])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut idle_hint = false;
+        let mut idle_since_usec = 0u64;
+        for line in stdout.lines() {
+            match line.split_once('=') {
+                Some(("IdleHint", value)) => idle_hint = value.trim() == "yes",
+                Some(("IdleSinceHint", value)) => idle_since_usec = value.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+
+        if !idle_hint || idle_since_usec == 0 {
+            return Some(Duration::ZERO);
+        }
+
+        let now_usec = Utc::now().timestamp_micros().max(0) as u64;
+        Some(Duration::from_micros(now_usec.saturating_sub(idle_since_usec)))
+    }
+
+    fn name(&self) -> &'static str {
+        "logind"
+    }
+}
+
+/// Idle time from IOKit's `HIDIdleTime`, read through `ioreg`.
+#[cfg(target_os = "macos")]
+pub struct IoKitIdleSource;
+
+#[cfg(target_os = "macos")]
+impl IdleSource for IoKitIdleSource {
+    fn idle_time(&self) -> Option<Duration> {
+        use std::process::Command;
+
+        let output = Command::new("ioreg").args(["-c", "IOHIDSystem", "-d", "4"]).output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let line = stdout.lines().find(|line| line.contains("\"HIDIdleTime\""))?;
+        let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+        Some(Duration::from_nanos(nanos))
+    }
+
+    fn name(&self) -> &'static str {
+        "iokit"
+    }
+}
+
+/// Picks the best idle source for this platform.
+pub fn default_idle_source() -> Option<Box<dyn IdleSource>> {
+    #[cfg(target_os = "linux")]
+    {
+        match X11IdleSource::connect() {
+            Ok(source) => return Some(Box::new(source)),
+            Err(e) => log::warn!("X11 idle detection unavailable ({}), falling back to logind", e),
+        }
+        Some(Box::new(LogindIdleSource::new()))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Some(Box::new(IoKitIdleSource))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+pub struct IdleMonitor;
+
+impl IdleMonitor {
+    pub fn spawn(state: AppState, source: Box<dyn IdleSource>) {
+        let source: Arc<dyn IdleSource> = Arc::from(source);
+        tokio::spawn(async move {
+            log::info!("Idle detection using {} source", source.name());
+            let mut ticker = interval(Duration::from_secs(5));
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = Self::poll(&state, &source).await {
+                    log::error!("Failed to record idle transition: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Takes one reading from `source` and records a transition if it crosses
+    /// `Config.idle_timeout_secs`.
+    async fn poll(state: &AppState, source: &Arc<dyn IdleSource>) -> Result<()> {
+        if state.is_paused().await {
+            return Ok(());
+        }
+
+        // Sources shell out (`loginctl`, `ioreg`) or wait on the X server
+        let source = Arc::clone(source);
+        let idle_time = tokio::task::spawn_blocking(move || source.idle_time())
+            .await
+            .map_err(|e| AppError::SystemMonitoring(e.to_string()))?;
+        let Some(idle_time) = idle_time else {
+            return Ok(());
+        };
+
+        let idle_timeout = Duration::from_secs(state.config().idle_timeout_secs);
+        let was_idle = state.is_idle().await;
+        let is_idle = idle_time >= idle_timeout;
+        if was_idle == is_idle {
+            return Ok(());
+        }
+
+        Self::transition(state, is_idle, idle_time).await
+    }
+
+    async fn transition(state: &AppState, is_idle: bool, idle_time: Duration) -> Result<()> {
+        let now = Utc::now();
+
+        // The user actually left at their last input, not when the timeout fired
+        let timestamp = if is_idle {
+            now - chrono::Duration::from_std(idle_time).unwrap_or_else(|_| chrono::Duration::zero())
+        } else {
+            now
+        };
+
+        let focus = state.focus.current().await;
+        if is_idle {
+            // Close the interval before flagging idle so the event monitor can't close it at `now`
+            state.focus.close(&state.writer, timestamp).await?;
+        }
+        state.set_idle(is_idle).await;
+
+        let event_type = if is_idle { EventType::Idle } else { EventType::Active };
+        let activity = WindowActivity {
+            id: None,
+            session_id: state.get_current_session_id().await,
+            app_id: focus.as_ref().map(|f| f.app_id.clone()).unwrap_or_else(|| "system".to_string()),
+            app_ref: focus.as_ref().and_then(|f| f.app_ref),
+            window_title: focus.map(|f| f.window_title).unwrap_or_default(),
+            event_type: event_type.as_str().to_string(),
+            timestamp,
+            duration: None,
+            metadata: None,
+        };
+
+        state.writer.activity(activity).await?;
+
+        log::info!("User is now {}", event_type.as_str());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::services::focus_tracker::CurrentFocus;
+
+    /// Replays a fixed sequence of idle readings, one per poll, then keeps
+    /// repeating the last one.
+    struct ScriptedIdleSource {
+        readings: Mutex<VecDeque<Duration>>,
+        last: Mutex<Duration>,
+    }
+
+    impl ScriptedIdleSource {
+        fn new(readings: Vec<Duration>) -> Self {
+            Self {
+                readings: Mutex::new(readings.into()),
+                last: Mutex::new(Duration::ZERO),
+            }
+        }
+    }
+
+    impl IdleSource for ScriptedIdleSource {
+        fn idle_time(&self) -> Option<Duration> {
+            let mut last = self.last.lock().unwrap();
+            if let Some(next) = self.readings.lock().unwrap().pop_front() {
+                *last = next;
+            }
+            Some(*last)
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+    }
+
+    #[tokio::test]
+    async fn idle_and_active_transitions_are_recorded_once() {
+        let state = AppState::in_memory().await;
+        let away = Duration::from_secs(state.config().idle_timeout_secs + 60);
+        let source: Arc<dyn IdleSource> = Arc::new(ScriptedIdleSource::new(vec![Duration::ZERO, away, away, Duration::ZERO]));
+
+        let since = Utc::now() - chrono::Duration::minutes(10);
+        let focus = CurrentFocus {
+            activity_id: None,
+            session_id: state.get_current_session_id().await,
+            app_id: "code".to_string(),
+            app_ref: None,
+            window_title: "main.rs".to_string(),
+            since,
+            sensitive: None,
+            bounds: None,
+        };
+        state.focus.begin(&state.writer, focus).await.unwrap();
+
+        IdleMonitor::poll(&state, &source).await.unwrap();
+        assert!(!state.is_idle().await);
+
+        let before_idle = Utc::now();
+        IdleMonitor::poll(&state, &source).await.unwrap();
+        let after_idle = Utc::now();
+        assert!(state.is_idle().await);
+        assert!(state.focus.current().await.is_none());
+
+        IdleMonitor::poll(&state, &source).await.unwrap();
+        assert!(state.is_idle().await);
+
+        IdleMonitor::poll(&state, &source).await.unwrap();
+        assert!(!state.is_idle().await);
+        // The last reading repeats, so nothing more happens
+        IdleMonitor::poll(&state, &source).await.unwrap();
+
+        state.writer.flush().await.unwrap();
+        let pool = state.repository.pool();
+
+        let rows: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT event_type, app_id, timestamp FROM window_activities ORDER BY id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        let events: Vec<(&str, &str)> = rows.iter().map(|(event, app, _)| (event.as_str(), app.as_str())).collect();
+        assert_eq!(events, vec![("idle", "code"), ("active", "system")]);
+
+        // Idle starts at the last input, not when the timeout was noticed
+        let idle_at = DateTime::parse_from_rfc3339(&rows[0].2).unwrap().with_timezone(&Utc);
+        let away = chrono::Duration::from_std(away).unwrap();
+        assert!(before_idle - away <= idle_at && idle_at <= after_idle - away);
+
+        let time_logs: Vec<(String, String)> = sqlx::query_as("SELECT focus_start, focus_end FROM time_logs")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        assert_eq!(time_logs, vec![(since.to_rfc3339(), rows[0].2.clone())]);
+    }
+}
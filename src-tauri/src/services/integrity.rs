@@ -0,0 +1,190 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+use tokio::time::{interval_at, Duration, Instant};
+
+use crate::error::{AppError, Result};
+use crate::models::{AuditKind, AuditLevel, IntegrityFailure, IntegrityStatus, Screenshot, VerificationReport};
+use crate::state::AppState;
+
+/// Rows checked per periodic pass, so a large archive is spread over several runs.
+const BATCH_SIZE: i64 = 500;
+
+/// How long a successful check stays valid before the file is read again.
+const REVERIFY_AFTER_DAYS: i64 = 7;
+
+/// Hex SHA-256, as stored in `screenshots.checksum`.
+pub fn checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn checksum_file(path: &Path) -> std::io::Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut length = 0u64;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        length += read as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), length))
+}
+
+/// Re-reads screenshot files and compares them with the size and checksum
+/// recorded at capture. Failures are written to `screenshots.integrity`.
+pub struct IntegrityVerifier;
+
+impl IntegrityVerifier {
+    pub fn spawn(app_handle: AppHandle, state: AppState) {
+        tokio::spawn(async move {
+            let mut ticker = interval_at(Instant::now() + Duration::from_secs(600), Duration::from_secs(3600));
+
+            loop {
+                ticker.tick().await;
+
+                let verified_before = Utc::now() - ChronoDuration::days(REVERIFY_AFTER_DAYS);
+                let due = match state
+                    .repository
+                    .get_screenshots_due_for_verification(verified_before, BATCH_SIZE)
+                    .await
+                {
+                    Ok(due) => due,
+                    Err(e) => {
+                        log::error!("Failed to load screenshots for verification: {}", e);
+                        continue;
+                    }
+                };
+                if due.is_empty() {
+                    continue;
+                }
+
+                if let Err(e) = Self::verify(&app_handle, &state, due).await {
+                    log::error!("Screenshot verification failed: {}", e);
+                }
+            }
+        });
+    }
+
+    pub async fn verify_range(
+        app_handle: &AppHandle,
+        state: &AppState,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<VerificationReport> {
+        let screenshots = state.repository.get_screenshots_in_range(from, to).await?;
+        Self::verify(app_handle, state, screenshots).await
+    }
+
+    async fn verify(app_handle: &AppHandle, state: &AppState, screenshots: Vec<Screenshot>) -> Result<VerificationReport> {
+        let mut report = VerificationReport::default();
+
+        for screenshot in screenshots {
+            // A row that can't be checked is still marked verified, so it doesn't
+            // come first in every later batch
+            let (status, backfill) = match Self::check(&screenshot).await {
+                Ok(checked) => checked,
+                Err(e) => {
+                    log::warn!("Failed to check screenshot {}: {}", screenshot.id, e);
+                    (IntegrityStatus::Unreadable, None)
+                }
+            };
+
+            report.checked += 1;
+            match status {
+                IntegrityStatus::Ok => report.ok += 1,
+                IntegrityStatus::Missing => report.missing += 1,
+                IntegrityStatus::Truncated => report.truncated += 1,
+                IntegrityStatus::Altered => report.altered += 1,
+                IntegrityStatus::Unreadable => report.unreadable += 1,
+            }
+            if backfill.is_some() {
+                report.backfilled += 1;
+            }
+            if status.is_corrupt() {
+                log::warn!("Screenshot {} is {}: {}", screenshot.id, status.as_str(), screenshot.path);
+                report.failures.push(IntegrityFailure {
+                    screenshot_id: screenshot.id,
+                    path: screenshot.path.clone(),
+                    status,
+                });
+            }
+
+            state
+                .repository
+                .set_screenshot_integrity(screenshot.id, status, backfill.as_deref(), Utc::now())
+                .await?;
+        }
+
+        log::info!(
+            "Verified {} screenshots: {} ok, {} missing, {} truncated, {} altered, {} unreadable",
+            report.checked,
+            report.ok,
+            report.missing,
+            report.truncated,
+            report.altered,
+            report.unreadable
+        );
+
+        if !report.failures.is_empty() {
+            state.cache.invalidate_screenshot_cache().await;
+
+            if let Err(e) = state
+                .audit
+                .record(
+                    AuditLevel::Warning,
+                    AuditKind::Integrity,
+                    &format!("{} screenshots failed verification", report.failures.len()),
+                    serde_json::to_value(&report.failures).ok(),
+                )
+                .await
+            {
+                log::error!("Failed to audit verification failures: {}", e);
+            }
+        }
+
+        if let Err(e) = app_handle.emit("screenshots-verified", &report) {
+            log::error!("Failed to emit verification report: {}", e);
+        }
+
+        Ok(report)
+    }
+
+    /// Returns the file's status, plus its checksum when the row has none yet.
+    pub async fn check(screenshot: &Screenshot) -> Result<(IntegrityStatus, Option<String>)> {
+        if !screenshot.has_image() {
+            return Ok((IntegrityStatus::Ok, None));
+        }
+
+        let path = screenshot.path.clone();
+        let hashed = tokio::task::spawn_blocking(move || checksum_file(Path::new(&path)))
+            .await
+            .map_err(|e| AppError::FileIO(format!("Verification task failed: {}", e)))?;
+
+        let (actual, length) = match hashed {
+            Ok(hashed) => hashed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((IntegrityStatus::Missing, None)),
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", screenshot.path, e);
+                return Ok((IntegrityStatus::Unreadable, None));
+            }
+        };
+
+        if (length as i64) < screenshot.file_size {
+            return Ok((IntegrityStatus::Truncated, None));
+        }
+
+        match &screenshot.checksum {
+            Some(expected) if *expected != actual => Ok((IntegrityStatus::Altered, None)),
+            Some(_) if length as i64 != screenshot.file_size => Ok((IntegrityStatus::Altered, None)),
+            Some(_) => Ok((IntegrityStatus::Ok, None)),
+            // No checksum to compare against; trust the file as it is now and record one
+            None => Ok((IntegrityStatus::Ok, Some(actual))),
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::interval;
+
+use crate::error::Result;
+use crate::models::{AppIdentity, EventType, WindowActivity, WindowMetadata};
+use crate::state::AppState;
+
+/// An application with at least one live process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningApp {
+    pub app: AppIdentity,
+    /// When its oldest process started, if known
+    pub started_at: Option<DateTime<Utc>>,
+    pub metadata: WindowMetadata,
+}
+
+/// A top-level window, whether or not it ever had focus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenWindow {
+    pub window_id: String,
+    pub app: AppIdentity,
+    pub title: String,
+    pub metadata: WindowMetadata,
+}
+
+/// Everything running at one instant.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LifecycleSnapshot {
+    pub apps: Vec<RunningApp>,
+    /// `None` when the platform has no window list; window events are then not recorded
+    pub windows: Option<Vec<OpenWindow>>,
+}
+
+/// Reports which apps and windows exist, for `LifecycleTracker` to diff.
+pub trait LifecycleSource: Send {
+    /// The current apps and windows, or `None` when the source cannot tell right now.
+    fn snapshot(&mut self) -> Option<LifecycleSnapshot>;
+
+    fn name(&self) -> &'static str;
+}
+
+/// Apps are the processes owning a window in the X11 client list; the sysinfo
+/// process table supplies their executable and start time.
+#[cfg(target_os = "linux")]
+pub struct X11LifecycleSource {
+    clients: crate::services::x11_focus::X11ClientList,
+    system: sysinfo::System,
+}
+
+#[cfg(target_os = "linux")]
+impl X11LifecycleSource {
+    pub fn connect() -> Result<Self> {
+        use sysinfo::SystemExt;
+
+        Ok(Self {
+            clients: crate::services::x11_focus::X11ClientList::connect(None)?,
+            system: sysinfo::System::new(),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LifecycleSource for X11LifecycleSource {
+    fn snapshot(&mut self) -> Option<LifecycleSnapshot> {
+        use sysinfo::{Pid, PidExt, ProcessExt, SystemExt};
+
+        let windows = match self.clients.windows() {
+            Ok(windows) => windows,
+            Err(e) => {
+                log::warn!("Failed to read the X11 client list: {}", e);
+                return None;
+            }
+        };
+        self.system.refresh_processes();
+
+        let mut apps: HashMap<String, RunningApp> = HashMap::new();
+        let mut open_windows = Vec::with_capacity(windows.len());
+        for window in windows {
+            let started_at = window
+                .metadata
+                .pid
+                .and_then(|pid| self.system.process(Pid::from_u32(pid)))
+                .and_then(|process| DateTime::from_timestamp(process.start_time() as i64, 0));
+
+            let running = apps.entry(window.app.stable_id()).or_insert_with(|| RunningApp {
+                app: window.app.clone(),
+                started_at,
+                metadata: WindowMetadata {
+                    source: "x11".to_string(),
+                    pid: window.metadata.pid,
+                    process_path: window.metadata.process_path.clone(),
+                    ..Default::default()
+                },
+            });
+            if started_at.is_some_and(|started_at| running.started_at.is_none_or(|current| started_at < current)) {
+                running.started_at = started_at;
+                running.metadata.pid = window.metadata.pid;
+            }
+
+            open_windows.push(OpenWindow {
+                window_id: window.metadata.window_id.clone().unwrap_or_default(),
+                app: window.app,
+                title: window.title,
+                metadata: window.metadata,
+            });
+        }
+
+        Some(LifecycleSnapshot {
+            apps: apps.into_values().collect(),
+            windows: Some(open_windows),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "x11-client-list"
+    }
+}
+
+/// Apps are the processes running from an application bundle
+/// (`*.app/Contents/MacOS/*`). There is no window list, so only launches and exits
+/// are recorded.
+#[cfg(target_os = "macos")]
+pub struct BundleLifecycleSource {
+    system: sysinfo::System,
+}
+
+#[cfg(target_os = "macos")]
+impl BundleLifecycleSource {
+    pub fn new() -> Self {
+        use sysinfo::SystemExt;
+
+        Self {
+            system: sysinfo::System::new(),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl LifecycleSource for BundleLifecycleSource {
+    fn snapshot(&mut self) -> Option<LifecycleSnapshot> {
+        use sysinfo::{PidExt, ProcessExt, SystemExt};
+
+        self.system.refresh_processes();
+
+        let mut apps: HashMap<String, RunningApp> = HashMap::new();
+        for (pid, process) in self.system.processes() {
+            let exe_path = process.exe().to_string_lossy().to_string();
+            // Helpers live in nested bundles; only the outermost one is the app
+            if exe_path.matches(".app/").count() != 1 || !exe_path.contains(".app/Contents/MacOS/") {
+                continue;
+            }
+
+            let app = AppIdentity {
+                name: process.name().to_string(),
+                exe_path: Some(exe_path.clone()),
+                ..Default::default()
+            };
+            let started_at = DateTime::from_timestamp(process.start_time() as i64, 0);
+            apps.entry(app.stable_id()).or_insert_with(|| RunningApp {
+                app,
+                started_at,
+                metadata: WindowMetadata {
+                    source: "process_table".to_string(),
+                    pid: Some(pid.as_u32()),
+                    process_path: Some(exe_path),
+                    ..Default::default()
+                },
+            });
+        }
+
+        Some(LifecycleSnapshot {
+            apps: apps.into_values().collect(),
+            windows: None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "process-table"
+    }
+}
+
+/// Picks the lifecycle source for this platform.
+pub fn default_lifecycle_source() -> Option<Box<dyn LifecycleSource>> {
+    #[cfg(target_os = "linux")]
+    {
+        match X11LifecycleSource::connect() {
+            Ok(source) => Some(Box::new(source)),
+            Err(e) => {
+                log::warn!("X11 client list unavailable ({}), lifecycle tracking disabled", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Some(Box::new(BundleLifecycleSource::new()))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// What the previous snapshot contained.
+struct Known {
+    taken_at: DateTime<Utc>,
+    apps: HashMap<String, RunningApp>,
+    windows: Option<HashMap<String, OpenWindow>>,
+}
+
+impl Known {
+    fn of(snapshot: LifecycleSnapshot, taken_at: DateTime<Utc>) -> Self {
+        Self {
+            taken_at,
+            apps: snapshot.apps.into_iter().map(|app| (app.app.stable_id(), app)).collect(),
+            windows: snapshot
+                .windows
+                .map(|windows| windows.into_iter().map(|window| (window.window_id.clone(), window)).collect()),
+        }
+    }
+}
+
+/// Records `AppOpen`/`AppClose` and `WindowCreate`/`WindowDestroy` by diffing
+/// consecutive snapshots, including apps and windows that never get focus.
+/// What already runs when tracking starts (or resumes) is the baseline and
+/// produces no events.
+///
+/// Lifecycle rows carry no `duration`: that column holds focus time, which the
+/// usage aggregates sum.
+pub struct LifecycleTracker;
+
+impl LifecycleTracker {
+    pub fn spawn(state: AppState, source: Box<dyn LifecycleSource>) {
+        tokio::spawn(async move {
+            log::info!("Lifecycle tracking using {} source", source.name());
+            let mut source = source;
+            let mut ticker = interval(Duration::from_secs(5));
+            let mut known: Option<Known> = None;
+
+            loop {
+                ticker.tick().await;
+
+                if state.is_paused().await {
+                    // Whatever happens while paused isn't recorded, even after resuming
+                    known = None;
+                    continue;
+                }
+
+                // Process tables and X round trips block
+                let Ok((returned, snapshot)) = tokio::task::spawn_blocking(move || {
+                    let snapshot = source.snapshot();
+                    (source, snapshot)
+                })
+                .await
+                else {
+                    log::error!("Lifecycle source panicked, lifecycle tracking stopped");
+                    return;
+                };
+                source = returned;
+
+                let Some(snapshot) = snapshot else {
+                    continue;
+                };
+
+                let now = Utc::now();
+                if let Some(previous) = known.take() {
+                    Self::diff(&state, previous, &snapshot, now).await;
+                }
+                known = Some(Known::of(snapshot, now));
+            }
+        });
+    }
+
+    async fn diff(
+        state: &AppState,
+        mut previous: Known,
+        snapshot: &LifecycleSnapshot,
+        now: DateTime<Utc>,
+    ) {
+        for app in &snapshot.apps {
+            if previous.apps.remove(&app.app.stable_id()).is_some() {
+                continue;
+            }
+
+            // The process start time is exact; otherwise all we know is "since the last poll"
+            let opened_at = app
+                .started_at
+                .filter(|started_at| *started_at > previous.taken_at && *started_at <= now)
+                .unwrap_or(now);
+            Self::record(state, LifecycleEvent::app(EventType::AppOpen, app, opened_at)).await;
+        }
+
+        if let (Some(mut previous_windows), Some(windows)) = (previous.windows, &snapshot.windows) {
+            for window in windows {
+                if previous_windows.remove(&window.window_id).is_none() {
+                    let event = LifecycleEvent::window(EventType::WindowCreate, window, now);
+                    Self::record(state, event).await;
+                }
+            }
+
+            for window in previous_windows.values() {
+                let event = LifecycleEvent::window(EventType::WindowDestroy, window, now);
+                Self::record(state, event).await;
+            }
+        }
+
+        // After the window events, so an app's windows go before the app does
+        for app in previous.apps.values() {
+            Self::record(state, LifecycleEvent::app(EventType::AppClose, app, now)).await;
+        }
+    }
+
+    async fn record(state: &AppState, event: LifecycleEvent<'_>) {
+        if let Err(e) = Self::insert(state, &event).await {
+            log::error!("Failed to record {} for {}: {}", event.event_type.as_str(), event.app.name, e);
+        }
+    }
+
+    async fn insert(state: &AppState, event: &LifecycleEvent<'_>) -> Result<()> {
+        let window_title = state.redactor().redact(&event.app.name, event.window_title);
+        let app_ref = state.writer.app_ref(event.app, event.timestamp).await?;
+
+        let activity = WindowActivity {
+            id: None,
+            session_id: state.get_current_session_id().await,
+            app_id: event.app.name.clone(),
+            app_ref: Some(app_ref),
+            window_title,
+            event_type: event.event_type.as_str().to_string(),
+            timestamp: event.timestamp,
+            duration: None,
+            metadata: serde_json::to_string(event.metadata).ok(),
+        };
+        state.writer.activity(activity).await?;
+        Ok(())
+    }
+}
+
+/// One `window_activities` row to write.
+struct LifecycleEvent<'a> {
+    event_type: EventType,
+    app: &'a AppIdentity,
+    window_title: &'a str,
+    timestamp: DateTime<Utc>,
+    metadata: &'a WindowMetadata,
+}
+
+impl<'a> LifecycleEvent<'a> {
+    fn app(event_type: EventType, app: &'a RunningApp, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            event_type,
+            app: &app.app,
+            window_title: "",
+            timestamp,
+            metadata: &app.metadata,
+        }
+    }
+
+    fn window(event_type: EventType, window: &'a OpenWindow, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            event_type,
+            app: &window.app,
+            window_title: &window.title,
+            timestamp,
+            metadata: &window.metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::error::AppError;
+
+    #[derive(Debug, Deserialize)]
+    struct ScriptedApp {
+        app_id: String,
+        #[serde(default)]
+        exe_path: Option<String>,
+        #[serde(default)]
+        windows: Vec<ScriptedWindow>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ScriptedWindow {
+        id: String,
+        #[serde(default)]
+        title: String,
+    }
+
+    /// Replays a fixed sequence of snapshots, one per poll, then keeps repeating the
+    /// last one. Drives lifecycle events without a desktop.
+    struct ScriptedLifecycleSource {
+        snapshots: VecDeque<LifecycleSnapshot>,
+        last: Option<LifecycleSnapshot>,
+    }
+
+    impl ScriptedLifecycleSource {
+        fn new(snapshots: Vec<LifecycleSnapshot>) -> Self {
+            Self {
+                snapshots: snapshots.into(),
+                last: None,
+            }
+        }
+
+        /// Parses a JSON array of snapshots, each a list of apps with their windows, e.g.
+        /// `[[{"app_id": "firefox", "windows": [{"id": "1", "title": "Docs"}]}], []]`.
+        fn parse(script: &str) -> Result<Self> {
+            let snapshots: Vec<Vec<ScriptedApp>> = serde_json::from_str(script)
+                .map_err(|e| AppError::Config(format!("Invalid lifecycle script: {}", e)))?;
+
+            Ok(Self::new(
+                snapshots
+                    .into_iter()
+                    .map(|apps| {
+                        let mut snapshot = LifecycleSnapshot {
+                            apps: Vec::new(),
+                            windows: Some(Vec::new()),
+                        };
+                        for scripted in apps {
+                            let app = AppIdentity {
+                                name: scripted.app_id,
+                                exe_path: scripted.exe_path,
+                                ..Default::default()
+                            };
+                            let metadata = WindowMetadata {
+                                source: "scripted".to_string(),
+                                ..Default::default()
+                            };
+
+                            if let Some(windows) = snapshot.windows.as_mut() {
+                                windows.extend(scripted.windows.into_iter().map(|window| OpenWindow {
+                                    metadata: WindowMetadata {
+                                        window_id: Some(window.id.clone()),
+                                        ..metadata.clone()
+                                    },
+                                    window_id: window.id,
+                                    app: app.clone(),
+                                    title: window.title,
+                                }));
+                            }
+                            snapshot.apps.push(RunningApp {
+                                app,
+                                started_at: None,
+                                metadata,
+                            });
+                        }
+                        snapshot
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    impl LifecycleSource for ScriptedLifecycleSource {
+        fn snapshot(&mut self) -> Option<LifecycleSnapshot> {
+            if let Some(next) = self.snapshots.pop_front() {
+                self.last = Some(next);
+            }
+            self.last.clone()
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_diffs_become_lifecycle_events() {
+        let state = AppState::in_memory().await;
+        let mut source = ScriptedLifecycleSource::parse(
+            r#"[
+                [{"app_id": "code", "windows": [{"id": "1", "title": "main.rs"}]}],
+                [
+                    {"app_id": "code", "windows": [{"id": "1", "title": "main.rs"}, {"id": "2", "title": "lib.rs"}]},
+                    {"app_id": "chat", "windows": [{"id": "3", "title": "general"}]}
+                ],
+                [{"app_id": "chat", "windows": [{"id": "3", "title": "general"}]}]
+            ]"#,
+        )
+        .unwrap();
+
+        // The first snapshot is the baseline
+        let started = Utc::now();
+        let mut known = Known::of(source.snapshot().unwrap(), started);
+        for _ in 0..2 {
+            let snapshot = source.snapshot().unwrap();
+            let now = Utc::now();
+            LifecycleTracker::diff(&state, known, &snapshot, now).await;
+            known = Known::of(snapshot, now);
+        }
+        state.writer.flush().await.unwrap();
+
+        let rows: Vec<(String, String, String, Option<i64>)> =
+            sqlx::query_as("SELECT event_type, app_id, window_title, duration FROM window_activities ORDER BY id")
+                .fetch_all(state.repository.pool())
+                .await
+                .unwrap();
+        let mut events: Vec<(&str, &str, &str)> =
+            rows.iter().map(|(event, app, title, _)| (event.as_str(), app.as_str(), title.as_str())).collect();
+        assert!(rows.iter().all(|(_, _, _, duration)| duration.is_none()));
+
+        // Windows that vanish together come out in no particular order
+        events[3..5].sort();
+        assert_eq!(
+            events,
+            vec![
+                ("app_open", "chat", ""),
+                ("window_create", "code", "lib.rs"),
+                ("window_create", "chat", "general"),
+                ("window_destroy", "code", "lib.rs"),
+                ("window_destroy", "code", "main.rs"),
+                ("app_close", "code", ""),
+            ]
+        );
+
+        // Lifecycle rows aren't focus time
+        let stats = state.repository.get_app_stats(started, Utc::now()).await.unwrap();
+        assert!(stats.is_empty());
+    }
+}
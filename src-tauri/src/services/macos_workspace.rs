@@ -0,0 +1,129 @@
+use cocoa::appkit::NSWorkspace;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSAutoreleasePool, NSString};
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use std::os::raw::c_void;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::services::window_source::{active_window, FocusedWindow, WindowSource};
+
+const OBSERVER_CLASS: &str = "SohamWorkspaceObserver";
+
+/// Reacts to `NSWorkspaceDidActivateApplicationNotification` so app switches are
+/// seen immediately. Title changes within an app raise no workspace notification,
+/// so the foreground window is also re-read on a short interval.
+pub struct WorkspaceWindowSource {
+    interval: Duration,
+}
+
+impl WorkspaceWindowSource {
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_millis(500),
+        }
+    }
+}
+
+impl Default for WorkspaceWindowSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowSource for WorkspaceWindowSource {
+    fn name(&self) -> &'static str {
+        "nsworkspace"
+    }
+
+    fn start(self: Box<Self>) -> mpsc::UnboundedReceiver<FocusedWindow> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (wake_sender, mut wake) = mpsc::unbounded_channel::<()>();
+
+        // The observer needs a run loop, which would block a runtime worker
+        let spawned = std::thread::Builder::new()
+            .name("nsworkspace-observer".to_string())
+            .spawn(move || unsafe { observe_activations(wake_sender) });
+        if let Err(e) = spawned {
+            log::warn!("Failed to start NSWorkspace observer, polling only: {}", e);
+        }
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            let mut last: Option<FocusedWindow> = None;
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    Some(()) = wake.recv() => {}
+                }
+
+                let Ok(Some(window)) = tokio::task::spawn_blocking(active_window).await else {
+                    continue;
+                };
+                if last.as_ref().is_some_and(|last| last.same_focus(&window)) {
+                    continue;
+                }
+
+                if sender.send(window.clone()).is_err() {
+                    break;
+                }
+                last = Some(window);
+            }
+        });
+
+        receiver
+    }
+}
+
+/// Registers the observer and runs this thread's run loop for the life of the
+/// process; each activation notification pokes `wake`. The observer and `wake`
+/// are never released.
+unsafe fn observe_activations(wake: mpsc::UnboundedSender<()>) {
+    let _pool = NSAutoreleasePool::new(nil);
+
+    let class = Class::get(OBSERVER_CLASS).unwrap_or_else(|| {
+        let mut decl = ClassDecl::new(OBSERVER_CLASS, class!(NSObject)).expect("observer class is declared once");
+        decl.add_ivar::<*const c_void>("_wake");
+        decl.add_method(
+            sel!(onAppActivated:),
+            on_app_activated as extern "C" fn(&Object, Sel, id),
+        );
+        decl.register()
+    });
+
+    let observer: id = msg_send![class, new];
+    let wake = Box::into_raw(Box::new(wake)) as *const c_void;
+    (*observer).set_ivar("_wake", wake);
+
+    let workspace = NSWorkspace::sharedWorkspace(nil);
+    let notification_center: id = msg_send![workspace, notificationCenter];
+    let notification_name = NSString::alloc(nil).init_str("NSWorkspaceDidActivateApplicationNotification");
+    let _: () = msg_send![notification_center,
+        addObserver: observer
+        selector: sel!(onAppActivated:)
+        name: notification_name
+        object: nil
+    ];
+
+    // A run loop without input sources returns from `run` at once; the port keeps
+    // this one alive
+    let run_loop: id = msg_send![class!(NSRunLoop), currentRunLoop];
+    let port: id = msg_send![class!(NSMachPort), port];
+    let default_mode = NSString::alloc(nil).init_str("kCFRunLoopDefaultMode");
+    let _: () = msg_send![run_loop, addPort: port forMode: default_mode];
+    loop {
+        let _: () = msg_send![run_loop, run];
+    }
+}
+
+extern "C" fn on_app_activated(this: &Object, _cmd: Sel, _notification: id) {
+    unsafe {
+        let wake = *this.get_ivar::<*const c_void>("_wake") as *const mpsc::UnboundedSender<()>;
+        if let Some(wake) = wake.as_ref() {
+            let _ = wake.send(());
+        }
+    }
+}
@@ -1,7 +1,31 @@
 pub mod screenshot;
+pub mod activity_writer;
+pub mod config_watcher;
 pub mod event_monitor;
+pub mod focus_tracker;
+pub mod idle;
+pub mod integrity;
+pub mod lifecycle;
+#[cfg(target_os = "macos")]
+pub mod macos_workspace;
+pub mod pause;
+pub mod redaction;
+pub mod retention;
+pub mod screen_state;
 pub mod system_monitor;
+pub mod window_source;
+#[cfg(target_os = "linux")]
+pub mod x11_focus;
 
 pub use screenshot::ScreenshotService;
+pub use activity_writer::ActivityWriter;
+pub use config_watcher::ConfigWatcher;
 pub use event_monitor::EventMonitor;
+pub use focus_tracker::FocusTracker;
+pub use idle::IdleMonitor;
+pub use integrity::IntegrityVerifier;
+pub use lifecycle::LifecycleTracker;
+pub use pause::PauseService;
+pub use redaction::HistoryRedaction;
+pub use retention::RetentionService;
 pub use system_monitor::SystemMonitor;
\ No newline at end of file
@@ -0,0 +1,231 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use tauri::{AppHandle, Emitter};
+use tokio::time::{sleep, Duration};
+
+use crate::error::{AppError, Result};
+use crate::models::{AuditKind, AuditLevel, PauseState, ResumeReason, ResumedPayload};
+use crate::state::AppState;
+
+const PAUSE_KEY: &str = "pause";
+
+/// How often a pause's end is checked against the wall clock. Tokio's timers stop
+/// while the system is suspended, so a single long sleep would overrun the pause.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time-limited pauses: persisted across restarts, capped by `Config.max_pause_secs`
+/// and resumed automatically when they run out.
+pub struct PauseService;
+
+impl PauseService {
+    /// Reinstates a pause persisted before the last exit, shortened to the current
+    /// `max_pause_secs`. One that ran out while the tracker was stopped is cleared
+    /// and audited as expired.
+    pub async fn restore(state: &AppState) -> Result<()> {
+        let Some(value) = state.repository.get_meta(PAUSE_KEY).await? else {
+            return Ok(());
+        };
+
+        let mut pause: PauseState = match serde_json::from_str(&value) {
+            Ok(pause) => pause,
+            Err(e) => {
+                log::warn!("Discarding unreadable persisted pause: {}", e);
+                return state.repository.delete_meta(PAUSE_KEY).await;
+            }
+        };
+
+        // The policy may have been tightened since the pause was taken
+        let now = Utc::now();
+        let max_pause = ChronoDuration::seconds(state.config().max_pause_secs as i64);
+        let latest = (pause.started_at + max_pause).min(now + max_pause);
+        if pause.until > latest {
+            log::warn!(
+                "Shortening persisted pause from {} to {} to fit max_pause_secs",
+                pause.until.to_rfc3339(),
+                latest.to_rfc3339()
+            );
+            pause.until = latest;
+            if pause.until > now {
+                let value = serde_json::to_string(&pause).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+                state.repository.set_meta(PAUSE_KEY, &value).await?;
+            }
+        }
+
+        if pause.until <= now {
+            state.repository.delete_meta(PAUSE_KEY).await?;
+            state
+                .audit
+                .info(AuditKind::Resume, "Pause expired while the tracker was stopped")
+                .await;
+            return Ok(());
+        }
+
+        log::info!("Restoring pause until {}", pause.until.to_rfc3339());
+        state.replace_pause(Some(pause));
+        Ok(())
+    }
+
+    /// Resumes tracking when the current pause runs out.
+    pub fn spawn(app_handle: AppHandle, state: AppState) {
+        tokio::spawn(async move {
+            let mut pause_rx = state.subscribe_pause();
+
+            loop {
+                let pause = pause_rx.borrow_and_update().clone();
+                let Some(pause) = pause else {
+                    if pause_rx.changed().await.is_err() {
+                        break;
+                    }
+                    continue;
+                };
+
+                let remaining = (pause.until - Utc::now()).to_std().unwrap_or_default();
+                tokio::select! {
+                    _ = sleep(remaining.min(EXPIRY_CHECK_INTERVAL)) => {
+                        // A pause replaced at the last moment is left for the next iteration
+                        if pause.until <= Utc::now() && state.pause_state().as_ref() == Some(&pause) {
+                            if let Err(e) = Self::resume(&app_handle, &state, ResumeReason::Expired).await {
+                                log::error!("Failed to resume after pause expired: {}", e);
+                            }
+                        }
+                    }
+                    changed = pause_rx.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Pauses for `duration_secs` (default `Config.default_pause_secs`), replacing any
+    /// pause already in effect.
+    pub async fn pause(
+        app_handle: &AppHandle,
+        state: &AppState,
+        duration_secs: Option<u64>,
+        reason: Option<String>,
+    ) -> Result<PauseState> {
+        let config = state.config();
+        let duration_secs = duration_secs.unwrap_or(config.default_pause_secs);
+        if duration_secs == 0 {
+            return Err(AppError::InvalidInput("Pause duration must be positive".to_string()));
+        }
+        if duration_secs > config.max_pause_secs {
+            return Err(AppError::InvalidInput(format!(
+                "Pause of {}s exceeds the policy maximum of {}s",
+                duration_secs, config.max_pause_secs
+            )));
+        }
+
+        let now = Utc::now();
+        let pause = PauseState {
+            started_at: now,
+            until: now + ChronoDuration::seconds(duration_secs as i64),
+            reason: reason.filter(|r| !r.trim().is_empty()),
+        };
+
+        let value = serde_json::to_string(&pause).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+        state.repository.set_meta(PAUSE_KEY, &value).await?;
+        state.replace_pause(Some(pause.clone()));
+
+        let message = match &pause.reason {
+            Some(reason) => format!("Tracking paused for {}s: {}", duration_secs, reason),
+            None => format!("Tracking paused for {}s", duration_secs),
+        };
+        if let Err(e) = state
+            .audit
+            .record(AuditLevel::Info, AuditKind::Pause, &message, serde_json::to_value(&pause).ok())
+            .await
+        {
+            log::error!("Failed to audit pause: {}", e);
+        }
+
+        if let Err(e) = app_handle.emit("paused", &pause) {
+            log::error!("Failed to emit paused event: {}", e);
+        }
+
+        log::info!("{}", message);
+        Ok(pause)
+    }
+
+    /// Ends the current pause, if any, and returns it.
+    pub async fn resume(app_handle: &AppHandle, state: &AppState, reason: ResumeReason) -> Result<Option<PauseState>> {
+        let Some(pause) = state.replace_pause(None) else {
+            return Ok(None);
+        };
+        state.repository.delete_meta(PAUSE_KEY).await?;
+
+        let message = match reason {
+            ResumeReason::User => "Tracking resumed by user",
+            ResumeReason::Expired => "Pause expired, tracking resumed",
+        };
+        if let Err(e) = state
+            .audit
+            .record(AuditLevel::Info, AuditKind::Resume, message, serde_json::to_value(&pause).ok())
+            .await
+        {
+            log::error!("Failed to audit resume: {}", e);
+        }
+
+        let payload = ResumedPayload {
+            reason,
+            pause: pause.clone(),
+        };
+        if let Err(e) = app_handle.emit("resumed", &payload) {
+            log::error!("Failed to emit resumed event: {}", e);
+        }
+
+        log::info!("{}", message);
+        Ok(Some(pause))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn persist(state: &AppState, pause: &PauseState) {
+        let value = serde_json::to_string(pause).unwrap();
+        state.repository.set_meta(PAUSE_KEY, &value).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn restored_pause_is_clamped_to_max_pause() {
+        let state = AppState::in_memory().await;
+        let max_pause = ChronoDuration::seconds(state.config().max_pause_secs as i64);
+        let started_at = Utc::now() - ChronoDuration::minutes(10);
+        persist(&state, &PauseState {
+            started_at,
+            until: started_at + max_pause * 3,
+            reason: None,
+        })
+        .await;
+
+        PauseService::restore(&state).await.unwrap();
+
+        let restored = state.pause_state().unwrap();
+        assert_eq!(restored.until, started_at + max_pause);
+        let persisted: PauseState =
+            serde_json::from_str(&state.repository.get_meta(PAUSE_KEY).await.unwrap().unwrap()).unwrap();
+        assert_eq!(persisted, restored);
+    }
+
+    #[tokio::test]
+    async fn restored_pause_past_max_pause_expires() {
+        let state = AppState::in_memory().await;
+        let max_pause = ChronoDuration::seconds(state.config().max_pause_secs as i64);
+        let started_at = Utc::now() - max_pause - ChronoDuration::minutes(1);
+        persist(&state, &PauseState {
+            started_at,
+            until: Utc::now() + ChronoDuration::hours(1),
+            reason: None,
+        })
+        .await;
+
+        PauseService::restore(&state).await.unwrap();
+
+        assert!(state.pause_state().is_none());
+        assert!(state.repository.get_meta(PAUSE_KEY).await.unwrap().is_none());
+    }
+}
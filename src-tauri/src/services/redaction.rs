@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use tokio::sync::Mutex;
+
+use crate::error::{AppError, Result};
+use crate::models::{AuditKind, AuditLevel, HistoryRedactionReport, RedactHistoryRequest, TitleRewrite, TitledTable};
+use crate::services::retention::delete_screenshot_files;
+use crate::state::AppState;
+
+/// Rows read and rewritten per transaction.
+const BATCH_SIZE: i64 = 500;
+
+/// Matches returned in `HistoryRedactionReport.preview`.
+const PREVIEW_LIMIT: usize = 50;
+
+static REDACTION_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Applies a title rewrite to history that was stored before the redaction rule
+/// existed: `window_activities`, `time_logs` and `screenshots`.
+pub struct HistoryRedaction;
+
+impl HistoryRedaction {
+    pub async fn run(state: &AppState, request: &RedactHistoryRequest) -> Result<HistoryRedactionReport> {
+        let _guard = REDACTION_LOCK.lock().await;
+
+        let regex = Regex::new(&request.pattern)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid pattern '{}': {}", request.pattern, e)))?;
+        let mut report = HistoryRedactionReport {
+            dry_run: request.dry_run,
+            ..Default::default()
+        };
+
+        let mut doomed_screenshots = Vec::new();
+        for table in TitledTable::ALL {
+            let delete = table == TitledTable::Screenshots && request.delete_screenshots;
+            let mut after_id = 0;
+
+            loop {
+                let page = state
+                    .repository
+                    .get_titles_page(table, request.from, request.to, after_id, BATCH_SIZE)
+                    .await?;
+                let Some((last_id, _)) = page.last() else {
+                    break;
+                };
+                after_id = *last_id;
+
+                let mut rewrites = Vec::new();
+                for (id, title) in page {
+                    if !regex.is_match(&title) {
+                        continue;
+                    }
+
+                    let after = (!delete).then(|| regex.replace_all(&title, request.replacement.as_str()).into_owned());
+                    if after.as_ref() == Some(&title) {
+                        continue;
+                    }
+
+                    if report.preview.len() < PREVIEW_LIMIT {
+                        report.preview.push(TitleRewrite {
+                            table,
+                            id,
+                            before: title,
+                            after: after.clone(),
+                        });
+                    }
+
+                    match after {
+                        Some(after) => rewrites.push((id, after)),
+                        None => doomed_screenshots.push(id),
+                    }
+                }
+
+                let changed = if request.dry_run {
+                    rewrites.len() as u64
+                } else {
+                    state.repository.update_titles(table, &rewrites).await?
+                };
+                match table {
+                    TitledTable::WindowActivities => report.activities_changed += changed,
+                    TitledTable::TimeLogs => report.time_logs_changed += changed,
+                    TitledTable::Screenshots => report.screenshots_changed += changed,
+                }
+            }
+        }
+
+        if request.dry_run {
+            report.screenshots_deleted = doomed_screenshots.len() as u64;
+            return Ok(report);
+        }
+
+        Self::delete_screenshots(state, doomed_screenshots, &mut report).await?;
+
+        state.cache.invalidate_dashboard_cache().await;
+        state.cache.invalidate_screenshot_cache().await;
+
+        log::info!(
+            "History redaction of /{}/ changed {} rows and deleted {} screenshots",
+            request.pattern,
+            report.rows_changed(),
+            report.screenshots_deleted
+        );
+        Self::audit(state, request, &report).await;
+
+        Ok(report)
+    }
+
+    async fn delete_screenshots(state: &AppState, ids: Vec<i64>, report: &mut HistoryRedactionReport) -> Result<()> {
+        let mut screenshots = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(screenshot) = state.repository.get_screenshot(id).await? {
+                screenshots.push(screenshot);
+            }
+        }
+
+        // Matching markers go first, so the markers left afterwards are exactly the ones
+        // that must survive their original
+        let (markers, originals): (Vec<_>, Vec<_>) = screenshots.into_iter().partition(|s| s.unchanged_of.is_some());
+        let marker_ids: Vec<i64> = markers.iter().map(|marker| marker.id).collect();
+        report.screenshots_deleted = state.repository.delete_screenshots(&marker_ids).await?;
+
+        let mut deletable = Vec::with_capacity(originals.len());
+        for screenshot in originals {
+            let id = screenshot.id;
+            // Skipped captures have no file
+            if !screenshot.has_image() {
+                deletable.push(id);
+                continue;
+            }
+
+            // Deleting the original would cascade to its remaining markers; one of them takes over the files
+            match state.repository.promote_unchanged_marker(id, DateTime::<Utc>::MIN_UTC).await {
+                Ok(Some(promoted)) => {
+                    log::debug!("Screenshot {} now owns the files of redacted screenshot {}", promoted, id);
+                    deletable.push(id);
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Failed to hand over screenshot {} to its markers: {}", id, e);
+                    continue;
+                }
+            }
+
+            match delete_screenshot_files(&screenshot).await {
+                Ok(removed) => report.files_deleted += removed.image as u64,
+                Err(e) => {
+                    log::warn!("Failed to delete screenshot {}: {}", screenshot.path, e);
+                    continue;
+                }
+            }
+            deletable.push(id);
+        }
+
+        report.screenshots_deleted += state.repository.delete_screenshots(&deletable).await?;
+        Ok(())
+    }
+
+    async fn audit(state: &AppState, request: &RedactHistoryRequest, report: &HistoryRedactionReport) {
+        let level = if report.screenshots_deleted > 0 {
+            AuditLevel::Warning
+        } else {
+            AuditLevel::Info
+        };
+        let message = format!(
+            "Redacted window titles matching /{}/: {} rows changed, {} screenshots deleted",
+            request.pattern,
+            report.rows_changed(),
+            report.screenshots_deleted
+        );
+        let metadata = serde_json::json!({
+            "pattern": request.pattern,
+            "replacement": request.replacement,
+            "from": request.from,
+            "to": request.to,
+            "delete_screenshots": request.delete_screenshots,
+            "activities_changed": report.activities_changed,
+            "time_logs_changed": report.time_logs_changed,
+            "screenshots_changed": report.screenshots_changed,
+            "screenshots_deleted": report.screenshots_deleted,
+            "files_deleted": report.files_deleted,
+        });
+
+        if let Err(e) = state.audit.record(level, AuditKind::Redaction, &message, Some(metadata)).await {
+            log::error!("Failed to audit history redaction: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_screenshot(state: &AppState, path: &str, title: &str, unchanged_of: Option<i64>) -> i64 {
+        sqlx::query(
+            r#"
+            INSERT INTO screenshots (session_id, path, timestamp, file_size, app_id, window_title, unchanged_of)
+            VALUES (?, ?, ?, 3, 'browser', ?, ?)
+            "#
+        )
+        .bind(state.get_current_session_id().await)
+        .bind(path)
+        .bind(Utc::now().to_rfc3339())
+        .bind(title)
+        .bind(unchanged_of)
+        .execute(state.repository.pool())
+        .await
+        .unwrap()
+        .last_insert_rowid()
+    }
+
+    fn delete_matching(pattern: &str) -> RedactHistoryRequest {
+        RedactHistoryRequest {
+            pattern: pattern.to_string(),
+            replacement: String::new(),
+            from: None,
+            to: None,
+            delete_screenshots: true,
+            dry_run: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn deleting_an_original_hands_its_file_to_unmatched_markers() {
+        let state = AppState::in_memory().await;
+        let path = std::env::temp_dir().join(format!("soham-redaction-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"png").unwrap();
+        let path_str = path.to_string_lossy().to_string();
+
+        let original = insert_screenshot(&state, &path_str, "Bank - statement", None).await;
+        let matched_marker = insert_screenshot(&state, &path_str, "Bank - transfer", Some(original)).await;
+        let kept_marker = insert_screenshot(&state, &path_str, "Inbox", Some(original)).await;
+
+        let report = HistoryRedaction::run(&state, &delete_matching("^Bank")).await.unwrap();
+
+        assert_eq!(report.screenshots_deleted, 2);
+        assert_eq!(report.files_deleted, 0);
+        assert!(path.exists());
+
+        let remaining: Vec<(i64, Option<i64>)> = sqlx::query_as("SELECT id, unchanged_of FROM screenshots")
+            .fetch_all(state.repository.pool())
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![(kept_marker, None)]);
+        assert!(state.repository.get_screenshot(matched_marker).await.unwrap().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn deleting_an_original_without_survivors_removes_its_file() {
+        let state = AppState::in_memory().await;
+        let path = std::env::temp_dir().join(format!("soham-redaction-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"png").unwrap();
+        let path_str = path.to_string_lossy().to_string();
+
+        let original = insert_screenshot(&state, &path_str, "Bank - statement", None).await;
+        insert_screenshot(&state, &path_str, "Bank - transfer", Some(original)).await;
+
+        let report = HistoryRedaction::run(&state, &delete_matching("^Bank")).await.unwrap();
+
+        assert_eq!((report.screenshots_deleted, report.files_deleted), (2, 1));
+        assert!(!path.exists());
+    }
+}
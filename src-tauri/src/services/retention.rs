@@ -0,0 +1,225 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use tokio::time::{interval_at, Duration, Instant};
+
+use crate::error::Result;
+use crate::models::{RetentionReport, Screenshot};
+use crate::state::AppState;
+
+/// Files without a database row are only removed once they are this old, so a
+/// capture that has been written but not yet inserted is never touched.
+const ORPHAN_GRACE: Duration = Duration::from_secs(3600);
+
+const SCREENSHOT_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+static RETENTION_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Enforces `Config.retention_days` on screenshots (rows and files), focus
+/// events, time logs and finished sessions.
+pub struct RetentionService;
+
+impl RetentionService {
+    pub fn spawn(app_handle: AppHandle, state: AppState) {
+        tokio::spawn(async move {
+            let mut ticker = interval_at(Instant::now() + Duration::from_secs(60), Duration::from_secs(3600));
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = Self::run(&app_handle, &state).await {
+                    log::error!("Retention run failed: {}", e);
+                }
+            }
+        });
+    }
+
+    pub async fn run(app_handle: &AppHandle, state: &AppState) -> Result<RetentionReport> {
+        let _guard = RETENTION_LOCK.lock().await;
+
+        let retention_days = state.config().retention_days;
+        let now = Utc::now();
+        let cutoff = now - ChronoDuration::days(retention_days as i64);
+        let mut report = RetentionReport {
+            ran_at: now,
+            cutoff,
+            ..Default::default()
+        };
+
+        // Screenshot files go first: deleting sessions below cascades to their rows.
+        // Markers and skipped captures have no file of their own. An original whose
+        // "unchanged" markers aren't all expired hands its file to the newest of them
+        // first, since deleting it cascades to the markers that still point at it.
+        let expired = state.repository.get_screenshots_before(cutoff).await?;
+        let mut expired_ids = Vec::with_capacity(expired.len());
+        for screenshot in expired {
+            let id = screenshot.id;
+            if screenshot.unchanged_of.is_some() || !screenshot.has_image() {
+                expired_ids.push(id);
+                continue;
+            }
+
+            match state.repository.promote_unchanged_marker(id, cutoff).await {
+                Ok(Some(promoted)) => {
+                    log::debug!("Screenshot {} now owns the files of expired screenshot {}", promoted, id);
+                    expired_ids.push(id);
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Failed to hand over screenshot {} to its markers: {}", id, e);
+                    continue;
+                }
+            }
+
+            match delete_screenshot_files(&screenshot).await {
+                Ok(removed) => {
+                    report.bytes_reclaimed += removed.bytes;
+                    if removed.image {
+                        report.files_deleted += 1;
+                    } else {
+                        report.missing_files += 1;
+                    }
+                }
+                Err(e) => {
+                    // Keep the row so the next run retries the file
+                    log::warn!("Failed to delete expired screenshot {}: {}", screenshot.path, e);
+                    continue;
+                }
+            }
+            expired_ids.push(id);
+        }
+        report.screenshots_deleted = state.repository.delete_screenshots(&expired_ids).await?;
+
+        let (activities, time_logs, sessions) = state
+            .repository
+            .delete_activity_before(cutoff, state.get_current_session_id().await)
+            .await?;
+        report.activities_deleted = activities;
+        report.time_logs_deleted = time_logs;
+        report.sessions_deleted = sessions;
+
+        let (orphans, orphan_bytes) = Self::remove_orphaned_files(state).await?;
+        report.orphaned_files_deleted = orphans;
+        report.bytes_reclaimed += orphan_bytes;
+
+        log::info!(
+            "Retention ({} days): removed {} screenshots, {} orphaned files, {} activities, {} sessions; reclaimed {} KB",
+            retention_days,
+            report.screenshots_deleted,
+            report.orphaned_files_deleted,
+            report.activities_deleted,
+            report.sessions_deleted,
+            report.bytes_reclaimed / 1024
+        );
+
+        state.cache.invalidate_dashboard_cache().await;
+        state.cache.invalidate_screenshot_cache().await;
+
+        if let Err(e) = app_handle.emit("retention-completed", &report) {
+            log::error!("Failed to emit retention report: {}", e);
+        }
+
+        Ok(report)
+    }
+
+    async fn remove_orphaned_files(state: &AppState) -> Result<(u64, u64)> {
+        let known: HashSet<PathBuf> = state
+            .repository
+            .get_all_screenshot_paths()
+            .await?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        let screenshot_dir = state.layout.screenshots_dir();
+
+        tokio::task::spawn_blocking(move || {
+            let mut files = Vec::new();
+            collect_screenshot_files(&screenshot_dir, &mut files);
+
+            let mut removed = 0;
+            let mut bytes = 0;
+            for (path, metadata) in files {
+                if known.contains(&path) {
+                    continue;
+                }
+
+                let age = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                    .unwrap_or_default();
+                if age < ORPHAN_GRACE {
+                    continue;
+                }
+
+                match std::fs::remove_file(&path) {
+                    Ok(()) => {
+                        removed += 1;
+                        bytes += metadata.len();
+                    }
+                    Err(e) => log::warn!("Failed to delete orphaned screenshot {}: {}", path.display(), e),
+                }
+            }
+
+            Ok((removed, bytes))
+        })
+        .await
+        .map_err(|e| crate::error::AppError::FileIO(format!("Orphan scan failed: {}", e)))?
+    }
+}
+
+/// What `delete_screenshot_files` removed.
+pub struct RemovedFiles {
+    /// False when the image was already gone
+    pub image: bool,
+    pub bytes: u64,
+}
+
+/// Deletes a screenshot's image and thumbnail. An error means the image is still
+/// on disk and the row should be kept.
+pub async fn delete_screenshot_files(screenshot: &Screenshot) -> std::io::Result<RemovedFiles> {
+    let mut removed = RemovedFiles { image: false, bytes: 0 };
+
+    if let Some(thumbnail_path) = &screenshot.thumbnail_path {
+        if let Ok(metadata) = tokio::fs::metadata(thumbnail_path).await {
+            if tokio::fs::remove_file(thumbnail_path).await.is_ok() {
+                removed.bytes += metadata.len();
+            }
+        }
+    }
+
+    if let Ok(metadata) = tokio::fs::metadata(&screenshot.path).await {
+        tokio::fs::remove_file(&screenshot.path).await?;
+        removed.image = true;
+        removed.bytes += metadata.len();
+    }
+
+    Ok(removed)
+}
+
+fn collect_screenshot_files(dir: &Path, files: &mut Vec<(PathBuf, std::fs::Metadata)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            collect_screenshot_files(&path, files);
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SCREENSHOT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        {
+            files.push((path, metadata));
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::error::{AppError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenState {
+    Active,
+    /// The session is locked; whatever is on screen is the lock screen
+    Locked,
+    /// The display is powered down (DPMS standby/suspend/off)
+    Asleep,
+}
+
+impl ScreenState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScreenState::Active => "active",
+            ScreenState::Locked => "locked",
+            ScreenState::Asleep => "asleep",
+        }
+    }
+}
+
+/// Reports whether capturing the screen right now would record anything the user is doing.
+pub trait ScreenStateSource: Send + Sync {
+    /// Current state, or `None` when the source cannot tell right now.
+    fn screen_state(&self) -> Option<ScreenState>;
+
+    fn name(&self) -> &'static str;
+}
+
+/// Lock state from systemd-logind's `LockedHint` session property.
+#[cfg(target_os = "linux")]
+pub struct LogindLockSource {
+    session_id: String,
+}
+
+#[cfg(target_os = "linux")]
+impl LogindLockSource {
+    pub fn new() -> Self {
+        Self {
+            session_id: std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string()),
+        }
+    }
+
+    fn is_locked(&self) -> Option<bool> {
+        use std::process::Command;
+
+        let output = Command::new("loginctl")
+            .args(["show-session", &self.session_id, "-p", "LockedHint", "--value"])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim() == "yes")
+    }
+}
+
+/// Display power state from the X11 DPMS extension.
+#[cfg(target_os = "linux")]
+pub struct X11DpmsSource {
+    connection: x11rb::rust_connection::RustConnection,
+}
+
+#[cfg(target_os = "linux")]
+impl X11DpmsSource {
+    pub fn connect() -> Result<Self> {
+        use x11rb::protocol::dpms::ConnectionExt;
+
+        let (connection, _) = x11rb::connect(None)
+            .map_err(|e| AppError::SystemMonitoring(format!("Failed to connect to X11: {}", e)))?;
+
+        connection
+            .dpms_info()
+            .map_err(|e| AppError::SystemMonitoring(e.to_string()))?
+            .reply()
+            .map_err(|e| AppError::SystemMonitoring(format!("DPMS unavailable: {}", e)))?;
+
+        Ok(Self { connection })
+    }
+
+    fn is_asleep(&self) -> Option<bool> {
+        use x11rb::protocol::dpms::{ConnectionExt, DPMSMode};
+
+        let reply = self.connection.dpms_info().ok()?.reply().ok()?;
+        // With DPMS disabled the monitor never powers down, whatever power_level says
+        Some(reply.state && reply.power_level != DPMSMode::ON)
+    }
+}
+
+/// logind for the lock, DPMS (when an X server is reachable) for display power.
+#[cfg(target_os = "linux")]
+pub struct LinuxScreenStateSource {
+    logind: LogindLockSource,
+    dpms: Option<X11DpmsSource>,
+}
+
+#[cfg(target_os = "linux")]
+impl LinuxScreenStateSource {
+    pub fn new() -> Self {
+        let dpms = match X11DpmsSource::connect() {
+            Ok(dpms) => Some(dpms),
+            Err(e) => {
+                log::warn!("Display sleep detection unavailable: {}", e);
+                None
+            }
+        };
+
+        Self {
+            logind: LogindLockSource::new(),
+            dpms,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ScreenStateSource for LinuxScreenStateSource {
+    fn screen_state(&self) -> Option<ScreenState> {
+        let asleep = self.dpms.as_ref().and_then(|dpms| dpms.is_asleep());
+        if asleep == Some(true) {
+            return Some(ScreenState::Asleep);
+        }
+
+        match self.logind.is_locked() {
+            Some(true) => Some(ScreenState::Locked),
+            Some(false) => Some(ScreenState::Active),
+            None => asleep.map(|_| ScreenState::Active),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.dpms.is_some() {
+            "logind+dpms"
+        } else {
+            "logind"
+        }
+    }
+}
+
+/// Replays a fixed sequence of states, one per poll, then keeps repeating the
+/// last one. Used to exercise lock handling without a real session.
+pub struct ScriptedScreenStateSource {
+    states: Mutex<VecDeque<ScreenState>>,
+    last: Mutex<ScreenState>,
+}
+
+impl ScriptedScreenStateSource {
+    /// Parses a comma-separated list such as `"active,locked,asleep,active"`.
+    pub fn parse(script: &str) -> Result<Self> {
+        let states = script
+            .split(',')
+            .map(|value| match value.trim() {
+                "active" => Ok(ScreenState::Active),
+                "locked" => Ok(ScreenState::Locked),
+                "asleep" => Ok(ScreenState::Asleep),
+                other => Err(AppError::Config(format!("Invalid screen state '{}'", other))),
+            })
+            .collect::<Result<VecDeque<_>>>()?;
+
+        Ok(Self {
+            states: Mutex::new(states),
+            last: Mutex::new(ScreenState::Active),
+        })
+    }
+}
+
+impl ScreenStateSource for ScriptedScreenStateSource {
+    fn screen_state(&self) -> Option<ScreenState> {
+        let mut last = self.last.lock().unwrap();
+        if let Some(next) = self.states.lock().unwrap().pop_front() {
+            *last = next;
+        }
+        Some(*last)
+    }
+
+    fn name(&self) -> &'static str {
+        "scripted"
+    }
+}
+
+/// Picks the lock/sleep detector for this platform. `SOHAM_SCREEN_STATE_SCRIPT`
+/// swaps in a `ScriptedScreenStateSource`.
+pub fn default_screen_state_source() -> Option<Box<dyn ScreenStateSource>> {
+    if let Ok(script) = std::env::var("SOHAM_SCREEN_STATE_SCRIPT") {
+        match ScriptedScreenStateSource::parse(&script) {
+            Ok(source) => return Some(Box::new(source)),
+            Err(e) => log::error!("Ignoring SOHAM_SCREEN_STATE_SCRIPT: {}", e),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Some(Box::new(LinuxScreenStateSource::new()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -1,13 +1,27 @@
-use chrono::Utc;
-use std::path::Path;
+use chrono::{DateTime, Utc};
+use image::{imageops, RgbaImage};
+use screenshots::{DisplayInfo, Screen};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
 use tokio::time::{interval, Duration};
 
+use crate::config::{CaptureMode, SensitiveAction};
+use crate::data_layout::{unique_path, DataLayout};
 use crate::database::Repository;
-use crate::error::Result;
-use crate::models::Screenshot;
+use crate::error::{AppError, Result};
+use crate::models::{AuditKind, AuditLevel, PrivacyAction, Screenshot};
+use crate::privacy::SensitiveMatch;
+use crate::screenshot_encoder::{perceptual_hash, EncodeBudget, EncodedImage, ScreenshotEncoder};
+use crate::services::focus_tracker::CurrentFocus;
+use crate::services::integrity::checksum;
+use crate::services::screen_state::{default_screen_state_source, ScreenState};
 use crate::state::AppState;
 
+/// Last stored (non-marker) capture per display; `None` keys the composite.
+type PreviousCaptures = HashMap<Option<i64>, Screenshot>;
+
 pub struct ScreenshotService;
 
 impl ScreenshotService {
@@ -15,20 +29,51 @@ impl ScreenshotService {
         repository: Repository,
         app_handle: AppHandle,
         state: AppState,
-        interval_secs: u64,
     ) {
         tokio::spawn(async move {
+            let screen_state_source = default_screen_state_source();
+            if let Some(source) = &screen_state_source {
+                log::info!("Screen lock detection using {} source", source.name());
+            }
+            let mut previous = PreviousCaptures::new();
+            let encode_budget = Arc::new(EncodeBudget::new());
+            let mut config_rx = state.subscribe_config();
+            let mut interval_secs = config_rx.borrow_and_update().screenshot_interval_secs;
             let mut ticker = interval(Duration::from_secs(interval_secs));
-            let screenshot_dir = Self::ensure_screenshot_directory().await;
 
             loop {
-                ticker.tick().await;
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    changed = config_rx.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+
+                        let new_interval = config_rx.borrow_and_update().screenshot_interval_secs;
+                        if new_interval != interval_secs {
+                            log::info!("Screenshot interval changed from {}s to {}s", interval_secs, new_interval);
+                            interval_secs = new_interval;
+                            ticker = interval(Duration::from_secs(interval_secs));
+                            // The first tick of a fresh interval fires immediately; start counting from now instead
+                            ticker.reset();
+                        }
+                        continue;
+                    }
+                }
 
-                if state.is_paused().await {
+                if state.is_paused().await || state.is_idle().await {
                     continue;
                 }
 
-                if let Err(e) = Self::capture_screenshot(&repository, &app_handle, &state, &screenshot_dir).await {
+                if state.config().skip_when_locked {
+                    let screen_state = screen_state_source.as_ref().and_then(|source| source.screen_state());
+                    if let Some(screen_state @ (ScreenState::Locked | ScreenState::Asleep)) = screen_state {
+                        log::debug!("Skipping screenshot, screen is {}", screen_state.as_str());
+                        continue;
+                    }
+                }
+
+                if let Err(e) = Self::capture_screenshot(&repository, &app_handle, &state, &mut previous, &encode_budget).await {
                     log::error!("Screenshot capture failed: {}", e);
                 }
             }
@@ -39,62 +84,63 @@ impl ScreenshotService {
         repository: &Repository,
         app_handle: &AppHandle,
         state: &AppState,
-        screenshot_dir: &Path,
+        previous: &mut PreviousCaptures,
+        encode_budget: &Arc<EncodeBudget>,
     ) -> Result<()> {
-        let timestamp = Utc::now();
-        let filename = format!("screenshot_{}.png", timestamp.format("%Y%m%d_%H%M%S"));
-        let file_path = screenshot_dir.join(&filename);
-
-        let screenshot_result = tokio::task::spawn_blocking({
-            let file_path = file_path.clone();
-            move || {
-                use screenshots::Screen;
-                let screens = Screen::all().map_err(|e| {
-                    crate::error::AppError::Screenshot(format!("Failed to get screens: {}", e))
-                })?;
-                
-                let screen = screens.first().ok_or_else(|| {
-                    crate::error::AppError::Screenshot("No screens found".to_string())
-                })?;
-                
-                let image = screen.capture().map_err(|e| {
-                    crate::error::AppError::Screenshot(format!("Failed to capture screen: {}", e))
-                })?;
-                
-                image.save(&file_path).map_err(|e| {
-                    crate::error::AppError::Screenshot(format!("Failed to save screenshot: {}", e))
-                })?;
-                
-                Ok::<(), crate::error::AppError>(())
-            }
-        })
+        // Read once: the rows are tagged with this window and the privacy decision is made on it
+        let focus = state.focus.current().await;
+
+        let config = state.config();
+        let request = CaptureRequest {
+            layout: Arc::clone(&state.layout),
+            encoder: ScreenshotEncoder::new(config.encoding, Arc::clone(encode_budget)),
+            capture_mode: config.capture_mode,
+            focus: focus.clone(),
+            sensitive_action: config.privacy.action,
+            dedupe: config.dedupe,
+            previous: previous.clone(),
+            session_id: state.get_current_session_id().await,
+            timestamp: Utc::now(),
+        };
+
+        let screenshots = tokio::task::spawn_blocking(move || Self::capture_displays(&request))
         .await
-        .map_err(|e| crate::error::AppError::Screenshot(e.to_string()))?;
+        .map_err(|e| AppError::Screenshot(e.to_string()))??;
 
-        screenshot_result?;
+        // Every row of a tick shares the same privacy decision
+        let privacy = screenshots
+            .first()
+            .and_then(|s| Some((s.privacy_action.clone()?, s.privacy_rule.clone().unwrap_or_default())));
 
-        let file_size = tokio::fs::metadata(&file_path)
-            .await
-            .map_err(|e| crate::error::AppError::Screenshot(format!("Failed to get file size: {}", e)))?
-            .len() as i64;
+        let mut privacy_ids = Vec::new();
+        for mut screenshot in screenshots {
+            if let Some(focus) = &focus {
+                screenshot.app_id = Some(focus.app_id.clone());
+                screenshot.window_title = Some(focus.window_title.clone());
+            }
 
-        let screenshot = Screenshot {
-            id: 0,
-            session_id: state.get_current_session_id().await,
-            path: file_path.to_string_lossy().to_string(),
-            timestamp,
-            file_size,
-            app_id: None,
-            window_title: None,
-        };
+            let screenshot_id = repository.insert_screenshot(&screenshot).await?;
 
-        let screenshot_id = repository.insert_screenshot(&screenshot).await?;
+            let mut final_screenshot = screenshot;
+            final_screenshot.id = screenshot_id;
 
-        let mut final_screenshot = screenshot;
-        final_screenshot.id = screenshot_id;
+            if final_screenshot.privacy_action.is_some() {
+                privacy_ids.push(final_screenshot.id);
+            }
 
-        if let Err(e) = app_handle.emit("screenshot-captured", &final_screenshot) {
-            log::error!("Failed to emit screenshot event: {}", e);
+            // Markers are compared against the file they point at, not against each other
+            if final_screenshot.unchanged_of.is_none() && final_screenshot.has_image() {
+                let key = if final_screenshot.is_composite { None } else { final_screenshot.screen_id };
+                previous.insert(key, final_screenshot.clone());
+            }
+
+            if let Err(e) = app_handle.emit("screenshot-captured", &final_screenshot) {
+                log::error!("Failed to emit screenshot event: {}", e);
+            }
+        }
+
+        if let Some((action, rule)) = privacy {
+            Self::audit_privacy(state, action, rule, privacy_ids).await;
         }
 
         state.cache.invalidate_screenshot_cache().await;
@@ -102,16 +148,363 @@ impl ScreenshotService {
         Ok(())
     }
 
-    async fn ensure_screenshot_directory() -> std::path::PathBuf {
-        let screenshots_dir = dirs::document_dir()
-            .unwrap_or_else(|| std::env::current_dir().unwrap())
-            .join("Soham")
-            .join("screenshots");
+    async fn audit_privacy(state: &AppState, action: String, rule: String, screenshot_ids: Vec<i64>) {
+        let message = format!("Screenshot {} over a sensitive window ({})", action, rule);
+        let metadata = serde_json::json!({
+            "action": action,
+            "rule": rule,
+            "screenshot_ids": screenshot_ids,
+        });
+
+        if let Err(e) = state.audit.record(AuditLevel::Info, AuditKind::Privacy, &message, Some(metadata)).await {
+            log::error!("Failed to audit screenshot privacy action: {}", e);
+        }
+    }
+
+    /// Captures every connected display, encodes and writes the image files. A display
+    /// that fails is skipped; the capture only fails when nothing was saved.
+    fn capture_displays(request: &CaptureRequest) -> Result<Vec<Screenshot>> {
+        // Checked before capturing so a skipped frame never exists, even in memory
+        let sensitive = request
+            .focus
+            .as_ref()
+            .and_then(|focus| Some((focus, focus.sensitive.as_ref()?)));
+        let redact = match sensitive {
+            Some((focus, sensitive)) => {
+                let bounds = focus.bounds.map(|(x, y, width, height)| ScreenBounds { x, y, width, height });
+                if request.sensitive_action == SensitiveAction::Skip || bounds.is_none() {
+                    log::info!("Skipping screenshot, focused window matches {}", sensitive.rule);
+                    return Ok(vec![skipped_row(request, sensitive)]);
+                }
+                bounds.map(|bounds| (bounds, sensitive))
+            }
+            None => None,
+        };
+
+        let screens = Screen::all()
+            .map_err(|e| AppError::Screenshot(format!("Failed to get screens: {}", e)))?;
+        if screens.is_empty() {
+            return Err(AppError::Screenshot("No screens found".to_string()));
+        }
 
-        if let Err(e) = tokio::fs::create_dir_all(&screenshots_dir).await {
-            log::error!("Failed to create screenshot directory: {}", e);
+        let mut captures = Vec::with_capacity(screens.len());
+        let mut last_error = None;
+        for screen in &screens {
+            match screen.capture().map_err(|e| e.to_string()).and_then(convert_capture) {
+                Ok(mut image) => {
+                    if let Some((window, _)) = &redact {
+                        black_out(&mut image, &screen.display_info, window);
+                    }
+                    captures.push((screen.display_info, image));
+                }
+                Err(e) => {
+                    log::warn!("Failed to capture screen {}: {}", screen.display_info.id, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        if captures.is_empty() {
+            return Err(AppError::Screenshot(format!(
+                "Failed to capture screen: {}",
+                last_error.unwrap_or_default()
+            )));
         }
 
-        screenshots_dir
+        let mut screenshots = Self::store_captures(request, captures)?;
+        if let Some((_, sensitive)) = &redact {
+            log::info!("Redacted focused window matching {} from screenshot", sensitive.rule);
+            for screenshot in &mut screenshots {
+                screenshot.privacy_action = Some(PrivacyAction::Redacted.as_str().to_string());
+                screenshot.privacy_rule = Some(sensitive.rule.clone());
+            }
+        }
+        Ok(screenshots)
     }
-}
\ No newline at end of file
+
+    fn store_captures(request: &CaptureRequest, captures: Vec<(DisplayInfo, RgbaImage)>) -> Result<Vec<Screenshot>> {
+        if request.capture_mode == CaptureMode::Composite && captures.len() > 1 {
+            let (bounds, image) = stitch(&captures);
+            let mut screenshot = Self::store(request, None, image, bounds)?;
+            screenshot.is_composite = true;
+            return Ok(vec![screenshot]);
+        }
+
+        let mut screenshots = Vec::with_capacity(captures.len());
+        for (display, image) in captures {
+            let screen_id = display.id as i64;
+            match Self::store(request, Some(screen_id), image, ScreenBounds::of(&display)) {
+                Ok(mut screenshot) => {
+                    screenshot.screen_id = Some(screen_id);
+                    screenshot.scale_factor = Some(display.scale_factor as f64);
+                    screenshots.push(screenshot);
+                }
+                Err(e) => log::warn!("Failed to save screen {}: {}", screen_id, e),
+            }
+        }
+
+        if screenshots.is_empty() {
+            return Err(AppError::Screenshot("No screen could be saved".to_string()));
+        }
+        Ok(screenshots)
+    }
+
+    /// Writes the image and builds its row, or builds an "unchanged" marker when it
+    /// encodes to exactly the same bytes as the previous capture of this display.
+    fn store(request: &CaptureRequest, screen_id: Option<i64>, image: RgbaImage, bounds: ScreenBounds) -> Result<Screenshot> {
+        let hash = perceptual_hash(&image);
+        let encoded = request.encoder.encode(image)?;
+        let encoded_checksum = checksum(&encoded.bytes);
+
+        if request.dedupe {
+            // Only an exact match: frames that differ by a line of text hash the same
+            let previous = request
+                .previous
+                .get(&screen_id)
+                .filter(|previous| previous.checksum.as_deref() == Some(encoded_checksum.as_str()));
+            if let Some(previous) = previous {
+                return Ok(unchanged_row(request, previous, hash, bounds));
+            }
+        }
+
+        let extension = encoded.format.extension();
+        let path = request.layout.screenshot_path(request.timestamp, screen_id, extension);
+        let saved = Self::save_image(path, encoded)?;
+        let mut screenshot = screenshot_row(request.session_id, request.timestamp, &saved, encoded_checksum, bounds);
+        screenshot.phash = Some(hash as i64);
+        Ok(screenshot)
+    }
+
+    fn save_image(file_path: PathBuf, encoded: EncodedImage) -> Result<SavedImage> {
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::Screenshot(format!("Failed to create screenshot directory: {}", e)))?;
+        }
+        // Two captures in the same second get a suffixed name
+        let file_path = unique_path(file_path);
+
+        std::fs::write(&file_path, &encoded.bytes)
+            .map_err(|e| AppError::Screenshot(format!("Failed to save screenshot: {}", e)))?;
+
+        // The gallery falls back to generating a thumbnail on demand, so this is not fatal
+        let thumbnail_path = unique_path(DataLayout::thumbnail_path(&file_path));
+        let thumbnail_path = match std::fs::write(&thumbnail_path, &encoded.thumbnail) {
+            Ok(()) => Some(thumbnail_path),
+            Err(e) => {
+                log::warn!("Failed to save thumbnail {}: {}", thumbnail_path.display(), e);
+                None
+            }
+        };
+
+        Ok(SavedImage {
+            path: file_path,
+            thumbnail_path,
+            encoded,
+        })
+    }
+}
+
+/// Everything one capture tick needs on the blocking pool.
+struct CaptureRequest {
+    layout: Arc<DataLayout>,
+    encoder: ScreenshotEncoder,
+    capture_mode: CaptureMode,
+    /// The event monitor's focus when the tick started
+    focus: Option<CurrentFocus>,
+    sensitive_action: SensitiveAction,
+    dedupe: bool,
+    previous: PreviousCaptures,
+    session_id: i64,
+    timestamp: DateTime<Utc>,
+}
+
+/// `screenshots` is built against an older `image`; hand the raw pixels across.
+fn convert_capture(image: screenshots::image::RgbaImage) -> std::result::Result<RgbaImage, String> {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_raw(width, height, image.into_raw())
+        .ok_or_else(|| "Capture buffer does not match its dimensions".to_string())
+}
+
+/// A display rectangle in desktop (logical pixel) coordinates.
+#[derive(Debug, Clone, Copy)]
+struct ScreenBounds {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+impl ScreenBounds {
+    fn of(display: &DisplayInfo) -> Self {
+        Self {
+            x: display.x as i64,
+            y: display.y as i64,
+            width: display.width as i64,
+            height: display.height as i64,
+        }
+    }
+}
+
+/// Files written for one image.
+struct SavedImage {
+    path: PathBuf,
+    thumbnail_path: Option<PathBuf>,
+    encoded: EncodedImage,
+}
+
+fn screenshot_row(
+    session_id: i64,
+    timestamp: DateTime<Utc>,
+    saved: &SavedImage,
+    checksum: String,
+    bounds: ScreenBounds,
+) -> Screenshot {
+    let encoded = &saved.encoded;
+    Screenshot {
+        id: 0,
+        session_id,
+        path: saved.path.to_string_lossy().to_string(),
+        timestamp,
+        file_size: encoded.bytes.len() as i64,
+        app_id: None,
+        window_title: None,
+        screen_id: None,
+        screen_x: Some(bounds.x),
+        screen_y: Some(bounds.y),
+        screen_width: Some(bounds.width),
+        screen_height: Some(bounds.height),
+        scale_factor: None,
+        is_composite: false,
+        format: Some(encoded.format.as_str().to_string()),
+        checksum: Some(checksum),
+        integrity: None,
+        thumbnail_path: saved.thumbnail_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+        phash: None,
+        unchanged_of: None,
+        privacy_action: None,
+        privacy_rule: None,
+        url: None,
+        thumbnail_url: None,
+    }
+}
+
+/// Row for a capture withheld because a sensitive window had focus. It has no
+/// file, so the gap in the timeline reads as deliberate.
+fn skipped_row(request: &CaptureRequest, sensitive: &SensitiveMatch) -> Screenshot {
+    Screenshot {
+        id: 0,
+        session_id: request.session_id,
+        path: String::new(),
+        timestamp: request.timestamp,
+        file_size: 0,
+        app_id: None,
+        window_title: None,
+        screen_id: None,
+        screen_x: None,
+        screen_y: None,
+        screen_width: None,
+        screen_height: None,
+        scale_factor: None,
+        is_composite: false,
+        format: None,
+        checksum: None,
+        integrity: None,
+        thumbnail_path: None,
+        phash: None,
+        unchanged_of: None,
+        privacy_action: Some(PrivacyAction::Skipped.as_str().to_string()),
+        privacy_rule: Some(sensitive.rule.clone()),
+        url: None,
+        thumbnail_url: None,
+    }
+}
+
+/// Marker for a frame that matched `previous`: it points at that capture's files
+/// (path, size, checksum, thumbnail) instead of writing new ones.
+fn unchanged_row(request: &CaptureRequest, previous: &Screenshot, hash: u64, bounds: ScreenBounds) -> Screenshot {
+    Screenshot {
+        id: 0,
+        session_id: request.session_id,
+        timestamp: request.timestamp,
+        app_id: None,
+        window_title: None,
+        screen_x: Some(bounds.x),
+        screen_y: Some(bounds.y),
+        screen_width: Some(bounds.width),
+        screen_height: Some(bounds.height),
+        integrity: None,
+        phash: Some(hash as i64),
+        unchanged_of: Some(previous.id),
+        // Set by `capture_displays` when this tick redacted a window
+        privacy_action: None,
+        privacy_rule: None,
+        url: None,
+        thumbnail_url: None,
+        ..previous.clone()
+    }
+}
+
+/// Fills the part of `window` that lies on `display` with black, mapping desktop
+/// coordinates to the capture's pixels (which may be HiDPI).
+fn black_out(image: &mut RgbaImage, display: &DisplayInfo, window: &ScreenBounds) {
+    let display_bounds = ScreenBounds::of(display);
+    let left = window.x.max(display_bounds.x);
+    let top = window.y.max(display_bounds.y);
+    let right = (window.x + window.width).min(display_bounds.x + display_bounds.width);
+    let bottom = (window.y + window.height).min(display_bounds.y + display_bounds.height);
+    if left >= right || top >= bottom || display_bounds.width <= 0 || display_bounds.height <= 0 {
+        return;
+    }
+
+    let scale_x = image.width() as f64 / display_bounds.width as f64;
+    let scale_y = image.height() as f64 / display_bounds.height as f64;
+    // Round outwards so no sliver of the window survives
+    let x0 = ((left - display_bounds.x) as f64 * scale_x).floor() as u32;
+    let y0 = ((top - display_bounds.y) as f64 * scale_y).floor() as u32;
+    let x1 = (((right - display_bounds.x) as f64 * scale_x).ceil() as u32).min(image.width());
+    let y1 = (((bottom - display_bounds.y) as f64 * scale_y).ceil() as u32).min(image.height());
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            image.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+        }
+    }
+}
+
+/// Lays the captures out as they sit on the desktop. Displays with a lower
+/// scale factor are upscaled so the composite has uniform pixel density.
+fn stitch(captures: &[(DisplayInfo, RgbaImage)]) -> (ScreenBounds, RgbaImage) {
+    let min_x = captures.iter().map(|(d, _)| d.x).min().unwrap_or(0);
+    let min_y = captures.iter().map(|(d, _)| d.y).min().unwrap_or(0);
+    let max_x = captures.iter().map(|(d, _)| d.x + d.width as i32).max().unwrap_or(0);
+    let max_y = captures.iter().map(|(d, _)| d.y + d.height as i32).max().unwrap_or(0);
+    let scale = captures
+        .iter()
+        .map(|(d, _)| d.scale_factor)
+        .fold(1.0_f32, f32::max);
+
+    let canvas_width = ((max_x - min_x) as f32 * scale).round() as u32;
+    let canvas_height = ((max_y - min_y) as f32 * scale).round() as u32;
+    let mut canvas = RgbaImage::new(canvas_width, canvas_height);
+
+    for (display, image) in captures {
+        let target_width = (display.width as f32 * scale).round() as u32;
+        let target_height = (display.height as f32 * scale).round() as u32;
+        let offset_x = ((display.x - min_x) as f32 * scale).round() as i64;
+        let offset_y = ((display.y - min_y) as f32 * scale).round() as i64;
+
+        if image.dimensions() == (target_width, target_height) {
+            imageops::overlay(&mut canvas, image, offset_x, offset_y);
+        } else {
+            let resized = imageops::resize(image, target_width, target_height, imageops::FilterType::Triangle);
+            imageops::overlay(&mut canvas, &resized, offset_x, offset_y);
+        }
+    }
+
+    let bounds = ScreenBounds {
+        x: min_x as i64,
+        y: min_y as i64,
+        width: (max_x - min_x) as i64,
+        height: (max_y - min_y) as i64,
+    };
+    (bounds, canvas)
+}
@@ -0,0 +1,300 @@
+use screenshots::{DisplayInfo, Screen};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::models::{AppIdentity, TransitionType, WindowMetadata};
+
+/// The window that holds keyboard focus, as reported by a `WindowSource`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusedWindow {
+    pub app: AppIdentity,
+    pub title: String,
+    /// Stored as `window_activities.metadata`
+    pub metadata: WindowMetadata,
+    /// The window appeared since the previous focus change. Only backends that see
+    /// the window list (X11) set this.
+    pub new_window: bool,
+    /// Focus moved here because the previously focused window closed. Only backends
+    /// that see the window list (X11) set this.
+    pub previous_closed: bool,
+}
+
+impl FocusedWindow {
+    /// Same app and title; backend details such as the pid don't count as a switch.
+    pub fn same_focus(&self, other: &FocusedWindow) -> bool {
+        self.app == other.app && self.title == other.title
+    }
+
+    /// Why focus moved from `previous` to this window. Opening or closing a window
+    /// wins over the app comparison, so `NewWindow`/`CloseWindow` may cross apps.
+    pub fn transition_from(&self, previous: &FocusedWindow) -> TransitionType {
+        if self.new_window {
+            TransitionType::NewWindow
+        } else if self.previous_closed {
+            TransitionType::CloseWindow
+        } else if self.app.stable_id() != previous.app.stable_id() {
+            TransitionType::AppSwitch
+        } else {
+            TransitionType::WindowSwitch
+        }
+    }
+}
+
+/// Yields focus changes for `EventMonitor`.
+pub trait WindowSource: Send {
+    fn name(&self) -> &'static str;
+
+    /// Starts the backend. Each focus or title change arrives on the returned
+    /// channel; the channel closes when the backend can no longer report focus.
+    fn start(self: Box<Self>) -> mpsc::UnboundedReceiver<FocusedWindow>;
+}
+
+/// Asks `active_win_pos_rs` for the foreground window on a fixed interval. Works
+/// everywhere, but misses switches shorter than the interval.
+pub struct PollingWindowSource {
+    interval: Duration,
+}
+
+impl PollingWindowSource {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+impl Default for PollingWindowSource {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(2))
+    }
+}
+
+impl WindowSource for PollingWindowSource {
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+
+    fn start(self: Box<Self>) -> mpsc::UnboundedReceiver<FocusedWindow> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            let mut last: Option<FocusedWindow> = None;
+
+            loop {
+                ticker.tick().await;
+
+                let Ok(Some(window)) = tokio::task::spawn_blocking(active_window).await else {
+                    continue;
+                };
+                if last.as_ref().is_some_and(|last| last.same_focus(&window)) {
+                    continue;
+                }
+
+                if sender.send(window.clone()).is_err() {
+                    break;
+                }
+                last = Some(window);
+            }
+        });
+
+        receiver
+    }
+}
+
+/// The foreground window from `active_win_pos_rs`, which works on every platform.
+pub fn active_window() -> Option<FocusedWindow> {
+    let window = active_win_pos_rs::get_active_window().ok()?;
+    let exe_path = Some(window.process_path.to_string_lossy().to_string()).filter(|path| !path.is_empty());
+    let position = &window.position;
+    let has_geometry = position.width >= 1.0 && position.height >= 1.0;
+
+    Some(FocusedWindow {
+        metadata: WindowMetadata {
+            source: "active_win".to_string(),
+            pid: u32::try_from(window.process_id).ok(),
+            process_path: exe_path.clone(),
+            window_id: Some(window.window_id.clone()).filter(|id| !id.is_empty()),
+            x: has_geometry.then(|| position.x.floor() as i64),
+            y: has_geometry.then(|| position.y.floor() as i64),
+            width: has_geometry.then(|| position.width.ceil() as i64),
+            height: has_geometry.then(|| position.height.ceil() as i64),
+            ..Default::default()
+        },
+        app: AppIdentity {
+            name: window.app_name,
+            exe_path,
+            ..Default::default()
+        },
+        title: window.title,
+        new_window: false,
+        previous_closed: false,
+    })
+}
+
+/// The connected displays, in the order `WindowMetadata.monitor` indexes.
+pub async fn displays() -> Vec<DisplayInfo> {
+    tokio::task::spawn_blocking(|| {
+        Screen::all()
+            .map(|screens| screens.into_iter().map(|screen| screen.display_info).collect())
+            .unwrap_or_default()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Sets `monitor` to the display holding most of the window and, unless the backend
+/// already knew, `fullscreen` to whether the window covers that whole display.
+pub fn locate(metadata: &mut WindowMetadata, displays: &[DisplayInfo]) {
+    let (Some(x), Some(y), Some(width), Some(height)) = (metadata.x, metadata.y, metadata.width, metadata.height) else {
+        return;
+    };
+
+    let overlap = |display: &DisplayInfo| {
+        let (left, top) = (x.max(display.x as i64), y.max(display.y as i64));
+        let right = (x + width).min(display.x as i64 + display.width as i64);
+        let bottom = (y + height).min(display.y as i64 + display.height as i64);
+        (right - left).max(0) * (bottom - top).max(0)
+    };
+    let Some((index, display)) = displays
+        .iter()
+        .enumerate()
+        .filter(|(_, display)| overlap(display) > 0)
+        .max_by_key(|(_, display)| overlap(display))
+    else {
+        return;
+    };
+
+    metadata.monitor = Some(index as u32);
+    if metadata.fullscreen.is_none() {
+        metadata.fullscreen = Some(
+            x <= display.x as i64
+                && y <= display.y as i64
+                && x + width >= display.x as i64 + display.width as i64
+                && y + height >= display.y as i64 + display.height as i64,
+        );
+    }
+}
+
+/// Picks the focus backend for this platform: X11 events on Linux (polling without an
+/// X server), NSWorkspace notifications on macOS, polling elsewhere.
+pub fn default_window_source() -> Box<dyn WindowSource> {
+    #[cfg(target_os = "linux")]
+    {
+        match crate::services::x11_focus::X11WindowSource::connect(None) {
+            Ok(source) => return Box::new(source),
+            Err(e) => log::warn!("X11 focus events unavailable ({}), falling back to polling", e),
+        }
+        Box::new(PollingWindowSource::default())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(crate::services::macos_workspace::WorkspaceWindowSource::new())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        Box::new(PollingWindowSource::default())
+    }
+}
+
+#[cfg(test)]
+pub mod scripted {
+    use serde::Deserialize;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    use super::{FocusedWindow, WindowSource};
+    use crate::error::{AppError, Result};
+    use crate::models::{AppIdentity, WindowMetadata};
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct ScriptStep {
+        /// Delay before this window takes focus, counted from the previous step
+        #[serde(default)]
+        after_ms: u64,
+        app_id: String,
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        exe_path: Option<String>,
+        #[serde(default)]
+        wm_class: Option<String>,
+        #[serde(default)]
+        desktop_entry: Option<String>,
+        #[serde(default)]
+        metadata: WindowMetadata,
+        #[serde(default)]
+        new_window: bool,
+        #[serde(default)]
+        previous_closed: bool,
+    }
+
+    /// Replays a fixed timeline of windows, then keeps the last one focused. Lets the
+    /// focus-to-database pipeline run without a desktop.
+    pub struct ScriptedWindowSource {
+        steps: Vec<(Duration, FocusedWindow)>,
+    }
+
+    impl ScriptedWindowSource {
+        pub fn new(steps: Vec<(Duration, FocusedWindow)>) -> Self {
+            Self { steps }
+        }
+
+        /// Parses a JSON array such as
+        /// `[{"app_id": "firefox", "title": "Docs"}, {"after_ms": 1500, "app_id": "code", "title": "main.rs"}]`;
+        /// steps may also carry `exe_path`, `wm_class`, `desktop_entry`, a `metadata` object
+        /// and the `new_window`/`previous_closed` flags.
+        pub fn parse(script: &str) -> Result<Self> {
+            let steps: Vec<ScriptStep> = serde_json::from_str(script)
+                .map_err(|e| AppError::Config(format!("Invalid window script: {}", e)))?;
+
+            Ok(Self::new(
+                steps
+                    .into_iter()
+                    .map(|step| {
+                        let window = FocusedWindow {
+                            app: AppIdentity {
+                                name: step.app_id,
+                                exe_path: step.exe_path,
+                                wm_class: step.wm_class,
+                                desktop_entry: step.desktop_entry,
+                            },
+                            title: step.title,
+                            metadata: WindowMetadata {
+                                source: "scripted".to_string(),
+                                ..step.metadata
+                            },
+                            new_window: step.new_window,
+                            previous_closed: step.previous_closed,
+                        };
+                        (Duration::from_millis(step.after_ms), window)
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    impl WindowSource for ScriptedWindowSource {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn start(self: Box<Self>) -> mpsc::UnboundedReceiver<FocusedWindow> {
+            let (sender, receiver) = mpsc::unbounded_channel();
+
+            tokio::spawn(async move {
+                for (delay, window) in self.steps {
+                    tokio::time::sleep(delay).await;
+                    if sender.send(window).is_err() {
+                        return;
+                    }
+                }
+
+                // Hold the channel open: the last window simply stays focused
+                sender.closed().await;
+            });
+
+            receiver
+        }
+    }
+}
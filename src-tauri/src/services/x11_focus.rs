@@ -0,0 +1,604 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::mpsc;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, EventMask, Window,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::error::{AppError, Result};
+use crate::models::{AppIdentity, WindowMetadata};
+use crate::services::window_source::{FocusedWindow, WindowSource};
+
+/// The focused top-level window as reported by the window manager.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct X11Window {
+    window_id: u32,
+    /// `WM_CLASS` as `(instance, class)`
+    wm_class: Option<(String, String)>,
+    pid: Option<u32>,
+    title: String,
+    /// `(x, y, width, height)` in root window coordinates
+    geometry: Option<(i64, i64, i64, i64)>,
+    fullscreen: bool,
+}
+
+impl X11Window {
+    /// The `WM_CLASS` class, which stays the same across windows and launches of an
+    /// app; falls back to the instance name, then the process name.
+    fn app_id(&self) -> String {
+        if let Some((instance, class)) = &self.wm_class {
+            if !class.is_empty() {
+                return class.clone();
+            }
+            if !instance.is_empty() {
+                return instance.clone();
+            }
+        }
+
+        self.pid
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+            .map(|comm| comm.trim().to_string())
+            .filter(|comm| !comm.is_empty())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    fn focused(&self) -> FocusedWindow {
+        let exe_path = self
+            .pid
+            .and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok())
+            .map(|path| path.to_string_lossy().to_string());
+        let (x, y, width, height) = match self.geometry {
+            Some((x, y, width, height)) => (Some(x), Some(y), Some(width), Some(height)),
+            None => (None, None, None, None),
+        };
+        let class = self
+            .wm_class
+            .as_ref()
+            .map(|(instance, class)| if class.is_empty() { instance } else { class })
+            .filter(|class| !class.is_empty());
+
+        FocusedWindow {
+            app: AppIdentity {
+                name: self.app_id(),
+                exe_path: exe_path.clone(),
+                wm_class: class.cloned(),
+                desktop_entry: self.wm_class.as_ref().and_then(|(instance, class)| desktop_entry(instance, class)),
+            },
+            title: self.title.clone(),
+            metadata: WindowMetadata {
+                source: "x11".to_string(),
+                pid: self.pid,
+                process_path: exe_path,
+                window_id: Some(self.window_id.to_string()),
+                x,
+                y,
+                width,
+                height,
+                fullscreen: Some(self.fullscreen),
+                monitor: None,
+            },
+            new_window: false,
+            previous_closed: false,
+        }
+    }
+}
+
+struct Atoms {
+    net_active_window: Atom,
+    net_client_list: Atom,
+    net_wm_name: Atom,
+    net_wm_pid: Atom,
+    net_wm_state: Atom,
+    net_wm_state_fullscreen: Atom,
+    utf8_string: Atom,
+}
+
+impl Atoms {
+    fn intern(connection: &RustConnection) -> Result<Self> {
+        let intern = |name: &[u8]| -> Result<Atom> {
+            Ok(connection
+                .intern_atom(false, name)
+                .map_err(x11_error)?
+                .reply()
+                .map_err(x11_error)?
+                .atom)
+        };
+
+        Ok(Self {
+            net_active_window: intern(b"_NET_ACTIVE_WINDOW")?,
+            net_client_list: intern(b"_NET_CLIENT_LIST")?,
+            net_wm_name: intern(b"_NET_WM_NAME")?,
+            net_wm_pid: intern(b"_NET_WM_PID")?,
+            net_wm_state: intern(b"_NET_WM_STATE")?,
+            net_wm_state_fullscreen: intern(b"_NET_WM_STATE_FULLSCREEN")?,
+            utf8_string: intern(b"UTF8_STRING")?,
+        })
+    }
+}
+
+/// Follows the focused window through `PropertyNotify` events instead of polling:
+/// `_NET_ACTIVE_WINDOW` on the root window for switches, `_NET_WM_NAME`/`WM_NAME`
+/// on the focused window for title changes.
+///
+/// Needs an EWMH window manager; any X server works, including Xvfb, where a test
+/// can drive it by setting `_NET_ACTIVE_WINDOW` and window names itself.
+pub struct X11WindowSource {
+    connection: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+impl X11WindowSource {
+    /// Connects to `display` (default `$DISPLAY`). Fails when there is no X server
+    /// or no EWMH window manager.
+    pub fn connect(display: Option<&str>) -> Result<Self> {
+        let (connection, screen_num) = x11rb::connect(display)
+            .map_err(|e| AppError::SystemMonitoring(format!("Failed to connect to X11: {}", e)))?;
+        let root = connection.setup().roots[screen_num].root;
+        let atoms = Atoms::intern(&connection)?;
+
+        // Without an EWMH window manager nothing ever sets _NET_ACTIVE_WINDOW
+        let supported = connection
+            .get_property(false, root, atoms.net_active_window, AtomEnum::WINDOW, 0, 1)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        if supported.type_ == u32::from(AtomEnum::NONE) {
+            return Err(AppError::SystemMonitoring(
+                "Window manager does not publish _NET_ACTIVE_WINDOW".to_string(),
+            ));
+        }
+
+        connection
+            .change_window_attributes(root, &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE))
+            .map_err(x11_error)?
+            .check()
+            .map_err(x11_error)?;
+
+        Ok(Self { connection, root, atoms })
+    }
+}
+
+impl WindowSource for X11WindowSource {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn start(self: Box<Self>) -> mpsc::UnboundedReceiver<FocusedWindow> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        // x11rb blocks on the socket, so the watcher gets its own thread
+        let spawned = std::thread::Builder::new().name("x11-focus".to_string()).spawn(move || {
+            if let Err(e) = watch(&self.connection, self.root, &self.atoms, &sender) {
+                log::warn!("X11 focus watcher stopped: {}", e);
+            }
+        });
+        if let Err(e) = spawned {
+            log::error!("Failed to start X11 focus watcher: {}", e);
+        }
+
+        receiver
+    }
+}
+
+/// Reads `_NET_CLIENT_LIST`, the window manager's list of managed top-level windows.
+pub struct X11ClientList {
+    connection: RustConnection,
+    root: Window,
+    atoms: Atoms,
+    /// Windows are described once; their identity doesn't change while they exist
+    described: HashMap<Window, FocusedWindow>,
+}
+
+impl X11ClientList {
+    /// Connects to `display` (default `$DISPLAY`). Fails when there is no X server
+    /// or the window manager doesn't publish a client list.
+    pub fn connect(display: Option<&str>) -> Result<Self> {
+        let (connection, screen_num) = x11rb::connect(display)
+            .map_err(|e| AppError::SystemMonitoring(format!("Failed to connect to X11: {}", e)))?;
+        let root = connection.setup().roots[screen_num].root;
+        let atoms = Atoms::intern(&connection)?;
+
+        let client_list = Self {
+            connection,
+            root,
+            atoms,
+            described: HashMap::new(),
+        };
+        if client_list.client_ids()?.is_none() {
+            return Err(AppError::SystemMonitoring(
+                "Window manager does not publish _NET_CLIENT_LIST".to_string(),
+            ));
+        }
+
+        Ok(client_list)
+    }
+
+    /// The managed windows, described the same way as focus changes (app identity,
+    /// title, window id and pid in the metadata).
+    pub fn windows(&mut self) -> Result<Vec<FocusedWindow>> {
+        let ids = self.client_ids()?.unwrap_or_default();
+        self.described.retain(|window, _| ids.contains(window));
+
+        let mut windows = Vec::with_capacity(ids.len());
+        for window in ids {
+            if !self.described.contains_key(&window) {
+                // Skip windows that vanished between the list and the lookup
+                let Some(described) = describe(&self.connection, window, &self.atoms) else {
+                    continue;
+                };
+                self.described.insert(window, described.focused());
+            }
+            windows.extend(self.described.get(&window).cloned());
+        }
+
+        Ok(windows)
+    }
+
+    fn client_ids(&self) -> Result<Option<Vec<Window>>> {
+        client_ids(&self.connection, self.root, &self.atoms)
+    }
+}
+
+/// `_NET_CLIENT_LIST`; `None` when the window manager doesn't publish it.
+fn client_ids(connection: &RustConnection, root: Window, atoms: &Atoms) -> Result<Option<Vec<Window>>> {
+    let reply = connection
+        .get_property(false, root, atoms.net_client_list, AtomEnum::WINDOW, 0, 4096)
+        .map_err(x11_error)?
+        .reply()
+        .map_err(x11_error)?;
+    if reply.type_ == u32::from(AtomEnum::NONE) {
+        return Ok(None);
+    }
+
+    Ok(reply.value32().map(|ids| ids.collect()))
+}
+
+fn watch(
+    connection: &RustConnection,
+    root: Window,
+    atoms: &Atoms,
+    sender: &mpsc::UnboundedSender<FocusedWindow>,
+) -> Result<()> {
+    let mut active = active_window(connection, root, atoms);
+    follow(connection, None, active)?;
+    let mut last = active.and_then(|window| describe(connection, window, atoms));
+    // The client list as of the last switch, to tell opened and closed windows apart
+    let mut clients = client_ids(connection, root, atoms).ok().flatten();
+    if let Some(window) = &last {
+        if sender.send(window.focused()).is_err() {
+            return Ok(());
+        }
+    }
+
+    loop {
+        let Event::PropertyNotify(event) = connection.wait_for_event().map_err(x11_error)? else {
+            continue;
+        };
+
+        if event.window == root && event.atom == atoms.net_active_window {
+            let now_active = active_window(connection, root, atoms);
+            if now_active != active {
+                follow(connection, active, now_active)?;
+                active = now_active;
+            }
+        } else if Some(event.window) != active
+            || (event.atom != atoms.net_wm_name && event.atom != u32::from(AtomEnum::WM_NAME))
+        {
+            continue;
+        }
+
+        let current = active.and_then(|window| describe(connection, window, atoms));
+        if let Some(window) = current.filter(|window| last.as_ref() != Some(window)) {
+            let mut focused = window.focused();
+            if let Some(previous) = last.as_ref().filter(|last| last.window_id != window.window_id) {
+                let now_clients = client_ids(connection, root, atoms).ok().flatten();
+                if let (Some(before), Some(now)) = (&clients, &now_clients) {
+                    focused.new_window = !before.contains(&window.window_id);
+                    focused.previous_closed = !now.contains(&previous.window_id);
+                }
+                clients = now_clients;
+            }
+
+            if sender.send(focused).is_err() {
+                return Ok(());
+            }
+            last = Some(window);
+        }
+    }
+}
+
+/// Moves the title subscription from the previously focused window to the new one.
+fn follow(connection: &RustConnection, previous: Option<Window>, next: Option<Window>) -> Result<()> {
+    if let Some(previous) = previous {
+        // The window may already be gone; that's fine
+        let _ = connection.change_window_attributes(previous, &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT));
+    }
+    if let Some(next) = next {
+        let _ = connection.change_window_attributes(next, &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE));
+    }
+    connection.flush().map_err(x11_error)?;
+    Ok(())
+}
+
+fn active_window(connection: &RustConnection, root: Window, atoms: &Atoms) -> Option<Window> {
+    let reply = connection
+        .get_property(false, root, atoms.net_active_window, AtomEnum::WINDOW, 0, 1)
+        .ok()?
+        .reply()
+        .ok()?;
+    let window = reply.value32()?.next()?;
+    (window != x11rb::NONE).then_some(window)
+}
+
+/// Reads the window's properties; `None` when it vanished in the meantime.
+fn describe(connection: &RustConnection, window: Window, atoms: &Atoms) -> Option<X11Window> {
+    let title = text_property(connection, window, atoms.net_wm_name, atoms.utf8_string)
+        .or_else(|| text_property(connection, window, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into()));
+
+    let wm_class = connection
+        .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+        .ok()?
+        .reply()
+        .ok()?;
+    let wm_class = {
+        let mut parts = wm_class.value.split(|b| *b == 0).map(|part| String::from_utf8_lossy(part).to_string());
+        match (parts.next(), parts.next()) {
+            (Some(instance), Some(class)) => Some((instance, class)),
+            (Some(instance), None) if !instance.is_empty() => Some((instance, String::new())),
+            _ => None,
+        }
+    };
+
+    let pid = connection
+        .get_property(false, window, atoms.net_wm_pid, AtomEnum::CARDINAL, 0, 1)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .and_then(|reply| reply.value32().and_then(|mut values| values.next()));
+
+    let fullscreen = connection
+        .get_property(false, window, atoms.net_wm_state, AtomEnum::ATOM, 0, 64)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .and_then(|reply| reply.value32().map(|mut states| states.any(|state| state == atoms.net_wm_state_fullscreen)))
+        .unwrap_or(false);
+
+    Some(X11Window {
+        window_id: window,
+        wm_class,
+        pid,
+        title: title.unwrap_or_default(),
+        geometry: geometry(connection, window),
+        fullscreen,
+    })
+}
+
+/// The window's rectangle relative to the root window. Reparenting window managers
+/// put clients inside a frame, so the position is translated rather than read.
+fn geometry(connection: &RustConnection, window: Window) -> Option<(i64, i64, i64, i64)> {
+    let geometry = connection.get_geometry(window).ok()?.reply().ok()?;
+    let origin = connection
+        .translate_coordinates(window, geometry.root, 0, 0)
+        .ok()?
+        .reply()
+        .ok()?;
+
+    Some((
+        origin.dst_x as i64,
+        origin.dst_y as i64,
+        geometry.width as i64,
+        geometry.height as i64,
+    ))
+}
+
+fn text_property(connection: &RustConnection, window: Window, property: Atom, type_: Atom) -> Option<String> {
+    let reply = connection
+        .get_property(false, window, property, type_, 0, 1024)
+        .ok()?
+        .reply()
+        .ok()?;
+    if reply.type_ == u32::from(AtomEnum::NONE) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&reply.value).to_string())
+}
+
+/// Desktop file ids already looked up, by `WM_CLASS`; the application dirs are
+/// only scanned the first time a class is seen.
+static DESKTOP_ENTRIES: LazyLock<Mutex<DesktopEntries>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// `(instance, class)` to desktop file id
+type DesktopEntries = HashMap<(String, String), Option<String>>;
+
+fn desktop_entry(instance: &str, class: &str) -> Option<String> {
+    let mut entries = DESKTOP_ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    entries
+        .entry((instance.to_string(), class.to_string()))
+        .or_insert_with(|| find_desktop_entry(instance, class))
+        .clone()
+}
+
+/// Prefers a `StartupWMClass` match, then a file named after the class or instance
+/// (`firefox.desktop`, `org.mozilla.firefox.desktop`).
+fn find_desktop_entry(instance: &str, class: &str) -> Option<String> {
+    let names: Vec<String> = [class, instance]
+        .iter()
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_lowercase())
+        .collect();
+    let mut by_name = None;
+
+    for dir in application_dirs() {
+        let Ok(files) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for file in files.flatten() {
+            let file_name = file.file_name().to_string_lossy().to_string();
+            let Some(stem) = file_name.strip_suffix(".desktop") else {
+                continue;
+            };
+
+            let startup_class = std::fs::read_to_string(file.path()).ok().and_then(|contents| {
+                contents
+                    .lines()
+                    .find_map(|line| line.strip_prefix("StartupWMClass=").map(|value| value.trim().to_string()))
+            });
+            if startup_class.is_some_and(|startup_class| !class.is_empty() && startup_class.eq_ignore_ascii_case(class)) {
+                return Some(file_name);
+            }
+
+            let stem = stem.to_lowercase();
+            if by_name.is_none()
+                && names.iter().any(|name| stem == *name || stem.ends_with(&format!(".{}", name)))
+            {
+                by_name = Some(file_name);
+            }
+        }
+    }
+
+    by_name
+}
+
+/// `$XDG_DATA_HOME/applications` followed by each `$XDG_DATA_DIRS` entry.
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+fn x11_error(e: impl std::fmt::Display) -> AppError {
+    AppError::SystemMonitoring(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use x11rb::protocol::xproto::{ConnectionExt as _, CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    use super::*;
+
+    /// Plays the window manager: creates windows and publishes `_NET_CLIENT_LIST`
+    /// and `_NET_ACTIVE_WINDOW` itself.
+    struct FakeWindowManager {
+        connection: RustConnection,
+        root: Window,
+        atoms: Atoms,
+    }
+
+    impl FakeWindowManager {
+        fn connect() -> Self {
+            let (connection, screen_num) = x11rb::connect(None).unwrap();
+            let root = connection.setup().roots[screen_num].root;
+            let atoms = Atoms::intern(&connection).unwrap();
+            Self { connection, root, atoms }
+        }
+
+        fn create(&self, instance: &str, class: &str, title: &str) -> Window {
+            let window = self.connection.generate_id().unwrap();
+            self.connection
+                .create_window(
+                    x11rb::COPY_DEPTH_FROM_PARENT,
+                    window,
+                    self.root,
+                    0,
+                    0,
+                    200,
+                    100,
+                    0,
+                    WindowClass::INPUT_OUTPUT,
+                    x11rb::COPY_FROM_PARENT,
+                    &CreateWindowAux::new(),
+                )
+                .unwrap();
+            let wm_class = format!("{}\0{}\0", instance, class);
+            self.connection
+                .change_property8(PropMode::REPLACE, window, AtomEnum::WM_CLASS, AtomEnum::STRING, wm_class.as_bytes())
+                .unwrap();
+            self.set_title(window, title);
+            window
+        }
+
+        fn set_title(&self, window: Window, title: &str) {
+            self.connection
+                .change_property8(PropMode::REPLACE, window, self.atoms.net_wm_name, self.atoms.utf8_string, title.as_bytes())
+                .unwrap();
+            self.connection.flush().unwrap();
+        }
+
+        /// The client list goes first, as a real window manager updates it before
+        /// activating a new window.
+        fn publish(&self, clients: &[Window], active: Window) {
+            self.connection
+                .change_property32(PropMode::REPLACE, self.root, self.atoms.net_client_list, AtomEnum::WINDOW, clients)
+                .unwrap();
+            self.connection
+                .change_property32(PropMode::REPLACE, self.root, self.atoms.net_active_window, AtomEnum::WINDOW, &[active])
+                .unwrap();
+            self.connection.flush().unwrap();
+        }
+    }
+
+    async fn next(windows: &mut mpsc::UnboundedReceiver<FocusedWindow>) -> FocusedWindow {
+        tokio::time::timeout(Duration::from_secs(5), windows.recv())
+            .await
+            .expect("no focus change within 5s")
+            .expect("X11 focus watcher stopped")
+    }
+
+    #[tokio::test]
+    #[ignore = "needs an X server, e.g. `xvfb-run cargo test -- --ignored`"]
+    async fn follows_active_window_and_titles() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAY is not set, skipping");
+            return;
+        }
+
+        let wm = FakeWindowManager::connect();
+        let editor = wm.create("editor", "Editor", "main.rs");
+        wm.publish(&[editor], editor);
+
+        let mut windows = Box::new(X11WindowSource::connect(None).unwrap()).start();
+
+        let focused = next(&mut windows).await;
+        assert_eq!(focused.app.name, "Editor");
+        assert_eq!(focused.app.wm_class.as_deref(), Some("Editor"));
+        assert_eq!(focused.title, "main.rs");
+        assert_eq!(focused.metadata.window_id, Some(editor.to_string()));
+
+        wm.set_title(editor, "lib.rs");
+        let focused = next(&mut windows).await;
+        assert_eq!((focused.app.name.as_str(), focused.title.as_str()), ("Editor", "lib.rs"));
+        assert!(!focused.new_window && !focused.previous_closed);
+
+        let terminal = wm.create("term", "Terminal", "shell");
+        wm.publish(&[editor, terminal], terminal);
+        let focused = next(&mut windows).await;
+        assert_eq!(focused.app.name, "Terminal");
+        assert_eq!(focused.app.wm_class.as_deref(), Some("Terminal"));
+        assert_eq!(focused.title, "shell");
+        assert!(focused.new_window && !focused.previous_closed);
+
+        wm.connection.destroy_window(terminal).unwrap();
+        wm.publish(&[editor], editor);
+        let focused = next(&mut windows).await;
+        assert_eq!((focused.app.name.as_str(), focused.title.as_str()), ("Editor", "lib.rs"));
+        assert!(!focused.new_window && focused.previous_closed);
+    }
+}
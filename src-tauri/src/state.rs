@@ -1,32 +1,98 @@
+use chrono::Utc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 use crate::cache::CacheManager;
-use crate::database::{DatabasePool, Repository};
+use crate::config::Config;
+use crate::data_layout::DataLayout;
+use crate::database::{AuditLog, DatabasePool, Repository};
+use crate::models::PauseState;
+use crate::privacy::TitleRedactor;
+use crate::services::{ActivityWriter, FocusTracker};
 
 pub struct AppState {
     pub repository: Repository,
+    pub audit: AuditLog,
     pub cache: Arc<CacheManager>,
-    paused: Arc<RwLock<bool>>,
+    pub focus: Arc<FocusTracker>,
+    pub writer: ActivityWriter,
+    pub layout: Arc<DataLayout>,
+    config: Arc<watch::Sender<Config>>,
+    /// Compiled from `config.redaction`; rebuilt whenever a new config is installed
+    redactor: Arc<watch::Sender<Arc<TitleRedactor>>>,
+    pause: Arc<watch::Sender<Option<PauseState>>>,
+    idle: Arc<RwLock<bool>>,
     current_session_id: Arc<RwLock<i64>>,
+    shutting_down: Arc<AtomicBool>,
 }
 
 impl AppState {
-    pub fn new(db_pool: DatabasePool) -> Self {
+    pub fn new(db_pool: DatabasePool, config: Config, layout: DataLayout) -> Self {
+        let redactor = Arc::new(compile_redactor(&config));
+
         Self {
             repository: Repository::new(db_pool.pool().clone()),
-            cache: Arc::new(CacheManager::new()),
-            paused: Arc::new(RwLock::new(false)),
+            audit: AuditLog::new(db_pool.pool().clone()),
+            cache: Arc::new(CacheManager::new(layout.icons_dir())),
+            focus: Arc::new(FocusTracker::new()),
+            writer: ActivityWriter::new(db_pool.pool().clone()),
+            layout: Arc::new(layout),
+            config: Arc::new(watch::channel(config).0),
+            redactor: Arc::new(watch::channel(redactor).0),
+            pause: Arc::new(watch::channel(None).0),
+            idle: Arc::new(RwLock::new(false)),
             current_session_id: Arc::new(RwLock::new(0)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub async fn set_paused(&self, paused: bool) {
-        *self.paused.write().await = paused;
+    pub fn config(&self) -> Config {
+        self.config.borrow().clone()
+    }
+
+    /// Receives every configuration accepted after startup.
+    pub fn subscribe_config(&self) -> watch::Receiver<Config> {
+        self.config.subscribe()
+    }
+
+    /// Installs a new configuration and returns the one it replaced.
+    pub fn replace_config(&self, config: Config) -> Config {
+        self.redactor.send_replace(Arc::new(compile_redactor(&config)));
+        self.config.send_replace(config)
+    }
+
+    /// The title redactor for the current configuration.
+    pub fn redactor(&self) -> Arc<TitleRedactor> {
+        Arc::clone(&self.redactor.borrow())
     }
 
+    /// True while a pause is in effect; an expired pause no longer counts even
+    /// before `PauseService` gets round to clearing it.
     pub async fn is_paused(&self) -> bool {
-        *self.paused.read().await
+        self.pause.borrow().as_ref().is_some_and(|pause| pause.until > Utc::now())
+    }
+
+    pub fn pause_state(&self) -> Option<PauseState> {
+        self.pause.borrow().clone()
+    }
+
+    /// Installs (or clears) the pause and returns the one it replaced. Use
+    /// `PauseService` to also persist, audit and emit the change.
+    pub fn replace_pause(&self, pause: Option<PauseState>) -> Option<PauseState> {
+        self.pause.send_replace(pause)
+    }
+
+    pub fn subscribe_pause(&self) -> watch::Receiver<Option<PauseState>> {
+        self.pause.subscribe()
+    }
+
+    pub async fn set_idle(&self, idle: bool) {
+        *self.idle.write().await = idle;
+    }
+
+    pub async fn is_idle(&self) -> bool {
+        *self.idle.read().await
     }
 
     pub async fn set_current_session_id(&self, session_id: i64) {
@@ -36,15 +102,48 @@ impl AppState {
     pub async fn get_current_session_id(&self) -> i64 {
         *self.current_session_id.read().await
     }
+
+    /// Returns `true` only for the first caller, so shutdown work runs once.
+    pub fn begin_shutdown(&self) -> bool {
+        !self.shutting_down.swap(true, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// Default config, an in-memory database with one open session and a scratch
+    /// data directory.
+    pub async fn in_memory() -> Self {
+        let db_pool = DatabasePool::in_memory().await.expect("in-memory database");
+        let layout = DataLayout::new(std::env::temp_dir().join(format!("soham-test-{}", uuid::Uuid::new_v4())));
+        let state = Self::new(db_pool, Config::default(), layout);
+
+        let session_id = state.repository.create_session(Utc::now()).await.expect("session");
+        state.set_current_session_id(session_id).await;
+        state
+    }
 }
 
 impl Clone for AppState {
     fn clone(&self) -> Self {
         Self {
             repository: Repository::new(self.repository.pool().clone()),
+            audit: self.audit.clone(),
             cache: Arc::clone(&self.cache),
-            paused: Arc::clone(&self.paused),
+            focus: Arc::clone(&self.focus),
+            writer: self.writer.clone(),
+            layout: Arc::clone(&self.layout),
+            config: Arc::clone(&self.config),
+            redactor: Arc::clone(&self.redactor),
+            pause: Arc::clone(&self.pause),
+            idle: Arc::clone(&self.idle),
             current_session_id: Arc::clone(&self.current_session_id),
+            shutting_down: Arc::clone(&self.shutting_down),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Only validated configurations are installed, and validation compiles the rules.
+fn compile_redactor(config: &Config) -> TitleRedactor {
+    TitleRedactor::new(&config.redaction).expect("redaction rules are checked by Config::validate")
+}
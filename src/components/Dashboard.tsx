@@ -107,7 +107,7 @@ function TopAppCard({ app, rank }: { app: AppStat; rank: number }) {
                 </div>
                 <AppIcon appId={app.app_id} className="h-8 w-8 rounded-lg shadow-sm" />
                 <div className="flex-1 min-w-0">
-                    <p className="text-sm font-medium truncate">{app.app_id}</p>
+                    <p className="text-sm font-medium truncate">{app.display_name}</p>
                     <p className="text-xs text-muted-foreground">
                         {formatDuration(app.total_duration)}
                     </p>
@@ -320,7 +320,7 @@ export function Dashboard() {
                                         mostUsedApp ? (
                                             <div className="flex items-center gap-2">
                                                 <AppIcon appId={mostUsedApp.app_id} className="h-5 w-5 rounded-md" />
-                                                <span className="truncate text-lg">{mostUsedApp.app_id}</span>
+                                                <span className="truncate text-lg">{mostUsedApp.display_name}</span>
                                             </div>
                                         ) : 'No data'
                                     }
@@ -355,7 +355,7 @@ export function Dashboard() {
                                     <ScrollArea className="h-full">
                                         <div className="space-y-2 pr-2">
                                             {data.app_stats.slice(0, 12).map((app, index) => (
-                                                <TopAppCard key={app.app_id} app={app} rank={index + 1} />
+                                                <TopAppCard key={app.stable_id} app={app} rank={index + 1} />
                                             ))}
                                         </div>
                                     </ScrollArea>
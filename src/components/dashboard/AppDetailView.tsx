@@ -83,7 +83,7 @@ export function AppDetailView({ app }: AppDetailViewProps) {
             <div className="flex items-center gap-4">
                 <AppIcon appId={app.app_id} className="h-12 w-12 rounded-lg shadow-sm" />
                 <div>
-                    <h1 className="text-2xl font-bold tracking-tight">{app.app_id}</h1>
+                    <h1 className="text-2xl font-bold tracking-tight">{app.display_name}</h1>
                     <p className="text-sm text-muted-foreground">
                         Last seen: {new Date(app.last_seen).toLocaleString()}
                     </p>
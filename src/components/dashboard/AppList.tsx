@@ -23,7 +23,7 @@ export function AppList({ apps, onAppSelect, selectedAppId }: AppListProps) {
             <div className="p-3 space-y-1">
                 {apps.map(app => (
                     <Button
-                        key={app.app_id}
+                        key={app.stable_id}
                         variant="ghost"
                         className={cn(
                             "w-full justify-start text-left h-auto p-3 hover:bg-muted/50 transition-all duration-200",
@@ -34,7 +34,7 @@ export function AppList({ apps, onAppSelect, selectedAppId }: AppListProps) {
                         <div className="flex items-center w-full gap-3">
                             <AppIcon appId={app.app_id} className="h-8 w-8 rounded-lg shadow-sm flex-shrink-0" />
                             <div className="flex-1 min-w-0">
-                                <p className="text-sm font-medium truncate">{app.app_id}</p>
+                                <p className="text-sm font-medium truncate">{app.display_name}</p>
                                 <p className="text-xs text-muted-foreground">
                                     {formatDuration(app.total_duration)}
                                 </p>
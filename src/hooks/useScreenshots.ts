@@ -5,8 +5,13 @@ import { startOfDay, endOfDay } from 'date-fns';
 // This is a simplified version of the one in db.rs
 // In a real app, you might share these types.
 export interface Screenshot {
-    path: string; // This will be base64 encoded image data
-    ts: number;
+    id: number;
+    path: string;
+    timestamp: string;
+    url?: string; // soham://localhost/screenshot/{id}?size=full; absent when the capture was skipped
+    thumbnail_url?: string; // soham://localhost/screenshot/{id}?size=thumb
+    privacy_action?: 'skipped' | 'redacted' | null;
+    privacy_rule?: string | null;
 }
 
 
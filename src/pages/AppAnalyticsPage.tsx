@@ -59,7 +59,7 @@ export function AppAnalyticsPage() {
                                 <div>
                                     <h1 className="text-xl font-bold tracking-tight">Application Analytics</h1>
                                     <p className="text-sm text-muted-foreground">
-                                        {selectedApp ? `Analyzing ${selectedApp.app_id}` : 'Detailed usage insights'}
+                                        {selectedApp ? `Analyzing ${selectedApp.display_name}` : 'Detailed usage insights'}
                                     </p>
                                 </div>
                             </div>
@@ -11,14 +11,25 @@ import { useScreenshots, Screenshot } from '@/hooks/useScreenshots';
 import { useVirtualizer } from '@tanstack/react-virtual';
 
 const ScreenshotCard: React.FC<{ screenshot: Screenshot }> = ({ screenshot }) => {
-    const ts = new Date(screenshot.ts * 1000);
-    const base64Image = `data:image/png;base64,${screenshot.path}`;
+    const ts = new Date(screenshot.timestamp);
+    if (screenshot.privacy_action === 'skipped') {
+        return (
+            <Card className="overflow-hidden h-full flex flex-col">
+                <div className="flex-grow flex items-center justify-center p-2 text-xs text-center text-muted-foreground bg-muted">
+                    Skipped: sensitive window ({screenshot.privacy_rule})
+                </div>
+                <div className="p-2 text-xs text-center border-t text-muted-foreground bg-card shrink-0">
+                    {format(ts, 'HH:mm:ss')}
+                </div>
+            </Card>
+        );
+    }
     return (
         <Dialog>
             <DialogTrigger asChild>
                 <Card className="overflow-hidden cursor-pointer group h-full flex flex-col">
                     <div className="flex-grow overflow-hidden">
-                        <img src={base64Image} alt={`Screenshot at ${ts}`} loading="lazy" className="object-cover w-full h-full transition-transform duration-300 group-hover:scale-105" />
+                        <img src={screenshot.thumbnail_url} alt={`Screenshot at ${ts}`} loading="lazy" className="object-cover w-full h-full transition-transform duration-300 group-hover:scale-105" />
                     </div>
                     <div className="p-2 text-xs text-center border-t text-muted-foreground bg-card shrink-0">
                         {format(ts, 'HH:mm:ss')}
@@ -29,7 +40,7 @@ const ScreenshotCard: React.FC<{ screenshot: Screenshot }> = ({ screenshot }) =>
                 <DialogHeader>
                     <DialogTitle>{format(ts, 'PPPp')}</DialogTitle>
                 </DialogHeader>
-                <img src={base64Image} alt={`Screenshot at ${ts}`} className="w-full rounded-md" />
+                <img src={screenshot.url} alt={`Screenshot at ${ts}`} className="w-full rounded-md" />
             </DialogContent>
         </Dialog>
     );
@@ -117,7 +128,7 @@ export const ScreenshotsPage: React.FC = () => {
                                     }}
                                 >
                                     {items.map(screenshot => (
-                                        <ScreenshotCard key={screenshot.ts} screenshot={screenshot} />
+                                        <ScreenshotCard key={screenshot.id} screenshot={screenshot} />
                                     ))}
                                 </div>
                             )
@@ -1,7 +0,0 @@
-use anyhow::Result;
-use chrono::Utc;
-use sysinfo::{Pid, System, SystemExt, ProcessExt};
-use tauri::{AppHandle, Emitter};
-use crate::state::AppState;
-
-// ... existing code ... 
\ No newline at end of file
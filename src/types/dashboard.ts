@@ -1,5 +1,8 @@
 export interface AppStat {
     app_id: string;
+    /** Identity that survives renames: `desktop:`, `exe:`, `wm_class:` or `name:` prefixed */
+    stable_id: string;
+    display_name: string;
     total_duration: number;
     session_count: number;
     avg_duration: number;